@@ -0,0 +1,313 @@
+use std::io;
+use std::sync::atomic::{AtomicU64, AtomicUsize, Ordering};
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+use tokio::sync::{OwnedSemaphorePermit, Semaphore};
+
+/// How often the controller re-evaluates the concurrency limit
+pub const ADJUST_INTERVAL: Duration = Duration::from_secs(1);
+
+/// Minimum time between two immediate cuts caused by resource exhaustion
+const EXHAUSTION_COOLDOWN: Duration = Duration::from_millis(100);
+
+/// Lowest concurrency the controller will back off to
+const MIN_LIMIT: usize = 16;
+
+/// Fewer finished connection attempts than this in a window are not enough to judge
+const MIN_SAMPLES: u64 = 20;
+
+/// Timeout ratio this far above the recent baseline is treated as congestion
+const TIMEOUT_RATIO_MARGIN: f64 = 0.15;
+
+/// Average connect latency this many times the best observed one is treated as congestion
+const LATENCY_FACTOR: f64 = 2.0;
+
+/// Outcome of a single connection attempt, as seen by the controller
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ConnectOutcome {
+    /// TCP connection established, with how long it took
+    Connected(Duration),
+    /// The host actively answered with an error (refused, unreachable, ...)
+    Failed,
+    /// No answer before the connect timeout
+    TimedOut,
+    /// Local resource exhaustion: out of file descriptors or ephemeral ports
+    ResourceExhausted,
+}
+
+impl ConnectOutcome {
+    /// Classify a failed `TcpStream::connect`
+    pub fn from_error(e: &io::Error) -> Self {
+        if is_resource_exhaustion(e) {
+            ConnectOutcome::ResourceExhausted
+        } else if e.kind() == io::ErrorKind::TimedOut {
+            ConnectOutcome::TimedOut
+        } else {
+            ConnectOutcome::Failed
+        }
+    }
+}
+
+/// Whether a connect error means we ran out of fds (EMFILE/ENFILE) or local ports (EADDRNOTAVAIL)
+fn is_resource_exhaustion(e: &io::Error) -> bool {
+    if e.kind() == io::ErrorKind::AddrNotAvailable {
+        return true;
+    }
+    #[cfg(unix)]
+    if let Some(code) = e.raw_os_error() {
+        return code == libc::EMFILE || code == libc::ENFILE || code == libc::EADDRNOTAVAIL;
+    }
+    false
+}
+
+/// Counters for the current evaluation window
+#[derive(Default)]
+struct Window {
+    connected: AtomicU64,
+    failed: AtomicU64,
+    timed_out: AtomicU64,
+    exhausted: AtomicU64,
+    latency_us: AtomicU64,
+}
+
+impl Window {
+    /// Read and reset all counters
+    fn take(&self) -> (u64, u64, u64, u64, u64) {
+        (
+            self.connected.swap(0, Ordering::Relaxed),
+            self.failed.swap(0, Ordering::Relaxed),
+            self.timed_out.swap(0, Ordering::Relaxed),
+            self.exhausted.swap(0, Ordering::Relaxed),
+            self.latency_us.swap(0, Ordering::Relaxed),
+        )
+    }
+}
+
+/// Baselines learned from earlier windows
+struct Baseline {
+    timeout_ratio: Option<f64>,
+    latency: Option<Duration>,
+}
+
+/// AIMD (additive increase, multiplicative decrease) concurrency controller
+///
+/// Gates scan tasks through a semaphore whose size follows the observed
+/// connection outcomes: it grows steadily while the network keeps up, and
+/// is cut back when timeouts spike above their recent level, connects get
+/// much slower than the best observed latency, or the system runs out of
+/// file descriptors or ephemeral ports.
+pub struct AdaptiveLimiter {
+    semaphore: Arc<Semaphore>,
+    limit: AtomicUsize,
+    max: usize,
+    /// Permits to retire as they are released (shrinking below the in-use count)
+    debt: Arc<AtomicUsize>,
+    window: Window,
+    baseline: Mutex<Baseline>,
+    last_exhaustion_cut: Mutex<Option<Instant>>,
+    decision: Mutex<String>,
+}
+
+/// Concurrency slot held for the duration of one target's scan
+pub struct Slot {
+    permit: Option<OwnedSemaphorePermit>,
+    debt: Arc<AtomicUsize>,
+}
+
+impl Drop for Slot {
+    fn drop(&mut self) {
+        // Pay off shrink debt by retiring this permit instead of returning it
+        let paid = self
+            .debt
+            .fetch_update(Ordering::Relaxed, Ordering::Relaxed, |d| d.checked_sub(1))
+            .is_ok();
+        if paid {
+            if let Some(permit) = self.permit.take() {
+                permit.forget();
+            }
+        }
+    }
+}
+
+impl AdaptiveLimiter {
+    /// Create a controller that never exceeds `max` concurrent targets
+    ///
+    /// Starts at a quarter of `max` and ramps up from there.
+    pub fn new(max: usize) -> Self {
+        let max = max.max(1);
+        let initial = (max / 4).max(MIN_LIMIT).min(max);
+        Self {
+            semaphore: Arc::new(Semaphore::new(initial)),
+            limit: AtomicUsize::new(initial),
+            max,
+            debt: Arc::new(AtomicUsize::new(0)),
+            window: Window::default(),
+            baseline: Mutex::new(Baseline {
+                timeout_ratio: None,
+                latency: None,
+            }),
+            last_exhaustion_cut: Mutex::new(None),
+            decision: Mutex::new(format!("conc {}", initial)),
+        }
+    }
+
+    /// Wait for a free concurrency slot
+    pub async fn acquire(&self) -> Slot {
+        let permit = self
+            .semaphore
+            .clone()
+            .acquire_owned()
+            .await
+            .expect("concurrency semaphore is never closed");
+        Slot {
+            permit: Some(permit),
+            debt: self.debt.clone(),
+        }
+    }
+
+    /// Record the outcome of one connection attempt
+    ///
+    /// Resource exhaustion halves the limit immediately (at most once per
+    /// [`EXHAUSTION_COOLDOWN`]) instead of waiting for the next window, since
+    /// failing connects burn through targets far faster than one per second.
+    pub fn record(&self, outcome: ConnectOutcome) {
+        if outcome == ConnectOutcome::ResourceExhausted {
+            let mut last = self.last_exhaustion_cut.lock().unwrap();
+            if last.is_none_or(|at| at.elapsed() >= EXHAUSTION_COOLDOWN) {
+                *last = Some(Instant::now());
+                let new = self.set_limit(self.limit() / 2);
+                self.decide(format!("conc {} ↓ (EMFILE/EADDRNOTAVAIL)", new));
+            }
+        }
+
+        let counter = match outcome {
+            ConnectOutcome::Connected(latency) => {
+                self.window
+                    .latency_us
+                    .fetch_add(latency.as_micros() as u64, Ordering::Relaxed);
+                &self.window.connected
+            }
+            ConnectOutcome::Failed => &self.window.failed,
+            ConnectOutcome::TimedOut => &self.window.timed_out,
+            ConnectOutcome::ResourceExhausted => &self.window.exhausted,
+        };
+        counter.fetch_add(1, Ordering::Relaxed);
+    }
+
+    /// Current concurrency limit
+    pub fn limit(&self) -> usize {
+        self.limit.load(Ordering::Relaxed)
+    }
+
+    /// Short description of the latest decision, for the progress line
+    pub fn decision(&self) -> String {
+        self.decision.lock().unwrap().clone()
+    }
+
+    /// Evaluate the last window and grow or shrink the limit
+    ///
+    /// Called every [`ADJUST_INTERVAL`] while a range is being scanned.
+    pub fn adjust(&self) {
+        let (connected, failed, timed_out, exhausted, latency_us) = self.window.take();
+        let limit = self.limit();
+
+        // Running out of fds or ports was already acted on in `record`; just don't grow
+        if exhausted > 0 {
+            self.decide(format!(
+                "conc {} ↓ ({} EMFILE/EADDRNOTAVAIL)",
+                limit, exhausted
+            ));
+            return;
+        }
+
+        let total = connected + failed + timed_out;
+        if total < MIN_SAMPLES {
+            self.decide(format!("conc {}", limit));
+            return;
+        }
+
+        let timeout_ratio = timed_out as f64 / total as f64;
+        let latency = (connected > 0).then(|| Duration::from_micros(latency_us / connected));
+
+        let mut baseline = self.baseline.lock().unwrap();
+        let timeout_spike = baseline
+            .timeout_ratio
+            .is_some_and(|base| timeout_ratio > base + TIMEOUT_RATIO_MARGIN);
+        let slow_connects = match (latency, baseline.latency) {
+            (Some(avg), Some(best)) => {
+                avg.as_secs_f64() > best.as_secs_f64() * LATENCY_FACTOR
+                    && avg > best + Duration::from_millis(5)
+            }
+            _ => false,
+        };
+
+        // Timeout baseline follows the recent past (target ranges differ in how
+        // many hosts are dead); latency baseline is the best seen so far
+        baseline.timeout_ratio = Some(match baseline.timeout_ratio {
+            Some(base) => base * 0.8 + timeout_ratio * 0.2,
+            None => timeout_ratio,
+        });
+        if let Some(avg) = latency {
+            baseline.latency = Some(baseline.latency.map_or(avg, |best| best.min(avg)));
+        }
+        drop(baseline);
+
+        if timeout_spike {
+            let new = self.set_limit(limit * 7 / 10);
+            self.decide(format!(
+                "conc {} ↓ (timeouts {:.0}%)",
+                new,
+                timeout_ratio * 100.0
+            ));
+        } else if slow_connects {
+            let new = self.set_limit(limit * 7 / 10);
+            self.decide(format!(
+                "conc {} ↓ (connect {}ms)",
+                new,
+                latency.unwrap_or_default().as_millis()
+            ));
+        } else if limit < self.max {
+            let new = self.set_limit(limit + (self.max / 32).max(1));
+            self.decide(format!("conc {} ↑", new));
+        } else {
+            self.decide(format!("conc {} (max)", limit));
+        }
+    }
+
+    /// Resize the semaphore to `target` (clamped to the allowed range)
+    fn set_limit(&self, target: usize) -> usize {
+        let target = target.clamp(MIN_LIMIT.min(self.max), self.max);
+        let current = self.limit.swap(target, Ordering::Relaxed);
+
+        if target > current {
+            // Cancel outstanding shrink debt before handing out new permits
+            let mut grow = target - current;
+            while grow > 0 {
+                let debt = self.debt.load(Ordering::Relaxed);
+                if debt == 0 {
+                    break;
+                }
+                let cancel = debt.min(grow);
+                if self
+                    .debt
+                    .compare_exchange(debt, debt - cancel, Ordering::Relaxed, Ordering::Relaxed)
+                    .is_ok()
+                {
+                    grow -= cancel;
+                }
+            }
+            self.semaphore.add_permits(grow);
+        } else if target < current {
+            // Remove idle permits now; the rest are retired as slots are released
+            let shrink = current - target;
+            let removed = self.semaphore.forget_permits(shrink);
+            self.debt.fetch_add(shrink - removed, Ordering::Relaxed);
+        }
+
+        target
+    }
+
+    fn decide(&self, decision: String) {
+        *self.decision.lock().unwrap() = decision;
+    }
+}
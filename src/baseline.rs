@@ -0,0 +1,626 @@
+//! Baseline: what the real site looks like through its public address
+//!
+//! Fetches the domain the way a browser would (DNS, usually a CDN edge),
+//! records what identifies the page and turns it into match rules for the
+//! scan, so origins serving the same site can be recognised without
+//! hand-written patterns.
+
+use crate::conn::{MaybeTlsStream, Scheme};
+use crate::http::{self, HttpResponse};
+use crate::matcher::{
+    self, AndMatcher, FaviconHashMatcher, HeaderMatcher, Matcher, NotMatcher, OrMatcher,
+    SimhashMatcher, StatusMatcher, TitleMatcher,
+};
+use crate::simhash;
+use crate::tls::{self, CertVerification};
+use regex::Regex;
+use std::net::{IpAddr, SocketAddr};
+use std::time::Duration;
+use tokio::io::AsyncWriteExt;
+use tokio::net::TcpStream;
+use tokio::time::{timeout, Instant};
+
+/// Headers every server or CDN may send; they say nothing about the site
+const GENERIC_HEADERS: &[&str] = &[
+    "accept-ranges",
+    "access-control-allow-origin",
+    "age",
+    "alt-svc",
+    "cache-control",
+    "connection",
+    "content-encoding",
+    "content-language",
+    "content-length",
+    "content-security-policy",
+    "content-type",
+    "cross-origin-opener-policy",
+    "cross-origin-resource-policy",
+    "date",
+    "etag",
+    "expires",
+    "keep-alive",
+    "last-modified",
+    "location",
+    "nel",
+    "permissions-policy",
+    "pragma",
+    "referrer-policy",
+    "report-to",
+    "server",
+    "server-timing",
+    "set-cookie",
+    "strict-transport-security",
+    "traceparent",
+    "transfer-encoding",
+    "vary",
+    "via",
+    "x-content-type-options",
+    "x-correlation-id",
+    "x-frame-options",
+    "x-request-id",
+    "x-trace-id",
+    "x-xss-protection",
+];
+
+/// Header name prefixes added by CDNs and caches rather than the origin
+const CDN_HEADER_PREFIXES: &[&str] = &[
+    "akamai-",
+    "cdn-",
+    "cf-",
+    "fastly-",
+    "x-akamai-",
+    "x-amz-",
+    "x-azure-",
+    "x-cache",
+    "x-cdn",
+    "x-edge-",
+    "x-fastly-",
+    "x-msedge-",
+    "x-served-by",
+    "x-timer",
+];
+
+/// `Server` values of CDN edges (lowercase substrings); origins answer with something else
+const CDN_SERVERS: &[&str] = &[
+    "akamaighost",
+    "bunnycdn",
+    "cloudflare",
+    "cloudfront",
+    "ddos-guard",
+    "ecacc",
+    "keycdn",
+    "sucuri",
+];
+
+/// Fewest stable body features worth a simhash rule; tiny bodies are all noise
+const MIN_FEATURES: usize = 8;
+
+/// How the baseline page is fetched
+#[derive(Debug, Clone)]
+pub struct BaselineOptions {
+    /// Connect to this address instead of the domain's DNS address (e.g. a known CDN edge)
+    pub address: Option<IpAddr>,
+    /// Port to connect to; by default 443, or 80 when the scheme is `http`
+    /// (in `auto` mode 443 is tried first, then 80)
+    pub port: Option<u16>,
+    /// Protocol, as for the scan: `auto` detects TLS on ports other than 80 and 443
+    pub scheme: Scheme,
+    /// Request path
+    pub path: String,
+    /// Extra request headers ("Header: Value")
+    pub headers: Vec<String>,
+    /// Budget for each connect, handshake and response
+    pub timeout: Duration,
+    /// Certificate verification for HTTPS
+    pub verification: CertVerification,
+    /// Maximum body bytes read
+    pub max_body: usize,
+}
+
+impl Default for BaselineOptions {
+    fn default() -> Self {
+        Self {
+            address: None,
+            port: None,
+            scheme: Scheme::Auto,
+            path: "/".to_string(),
+            headers: Vec::new(),
+            timeout: Duration::from_secs(10),
+            verification: CertVerification::SystemRoots,
+            max_body: 256 * 1024,
+        }
+    }
+}
+
+/// What identifies the site, as seen through its public address
+///
+/// The page is fetched twice: only headers with the same value both times,
+/// and only body features present both times, are kept, so nonces, CSRF
+/// tokens and request ids do not end up in the rules.
+#[derive(Debug, Clone)]
+pub struct Baseline {
+    /// Where the page was fetched from
+    pub endpoint: SocketAddr,
+    /// "http" or "https"
+    pub scheme: &'static str,
+    /// Status of the page
+    pub status: u16,
+    /// Page `<title>`, if any
+    pub title: Option<String>,
+    /// `Server` header, when it names a CDN edge rather than the origin
+    pub cdn_server: Option<String>,
+    /// Site-specific headers with stable values (generic and CDN headers left out)
+    pub headers: Vec<(String, String)>,
+    /// Simhash of the body features common to both fetches (`None` for near-empty bodies)
+    pub simhash: Option<u64>,
+    /// Number of body features behind `simhash`
+    pub features: usize,
+    /// Shodan-style hash of `/favicon.ico`, if the site serves one
+    pub favicon_hash: Option<i32>,
+}
+
+impl Baseline {
+    /// Fetch the baseline page (twice) and the favicon of a domain
+    ///
+    /// # Arguments
+    /// * `domain` - Domain to fetch; sent as Host header and TLS SNI
+    /// * `options` - Address override, scheme, path, timeouts and verification
+    ///
+    /// # Returns
+    /// * `Ok(Baseline)` - The page answered
+    /// * `Err` - If the domain did not resolve or no address/port combination answered
+    ///
+    /// # Behavior
+    /// - Tries every resolved address (or the override) until one answers
+    ///   both page fetches; without a port, `auto` tries HTTPS on 443 before
+    ///   HTTP on 80
+    /// - A missing or HTML favicon leaves `favicon_hash` empty instead of failing
+    pub async fn fetch(
+        domain: &str,
+        options: &BaselineOptions,
+    ) -> Result<Self, Box<dyn std::error::Error>> {
+        let addresses: Vec<IpAddr> = match options.address {
+            Some(address) => vec![address],
+            None => tokio::net::lookup_host((domain, 0))
+                .await
+                .map_err(|e| format!("Failed to resolve {}: {}", domain, e))?
+                .map(|addr| addr.ip())
+                .collect(),
+        };
+        if addresses.is_empty() {
+            return Err(format!("{} did not resolve to any address", domain).into());
+        }
+
+        let attempts = match (options.scheme, options.port) {
+            (Scheme::Http, port) => vec![(port.unwrap_or(80), false)],
+            (Scheme::Https, port) => vec![(port.unwrap_or(443), true)],
+            (Scheme::Auto, Some(port)) => match options.scheme.tls_for_port(port) {
+                Some(use_tls) => vec![(port, use_tls)],
+                None => vec![(port, true), (port, false)],
+            },
+            (Scheme::Auto, None) => vec![(443, true), (80, false)],
+        };
+
+        let fetcher = Fetcher::new(domain, options)?;
+        let endpoints = addresses.into_iter().flat_map(|address| {
+            attempts
+                .iter()
+                .map(move |&(port, use_tls)| (SocketAddr::new(address, port), use_tls))
+        });
+        Ok(Self::fetch_first(&fetcher, endpoints, &options.path).await?)
+    }
+
+    /// Fetch from the first endpoint that answers the page twice
+    async fn fetch_first(
+        fetcher: &Fetcher,
+        endpoints: impl IntoIterator<Item = (SocketAddr, bool)>,
+        path: &str,
+    ) -> Result<Self, String> {
+        let mut last_error = None;
+        for (endpoint, use_tls) in endpoints {
+            let page = async {
+                let first = fetcher.get(endpoint, use_tls, path).await?;
+                let second = fetcher.get(endpoint, use_tls, path).await?;
+                Ok::<_, Box<dyn std::error::Error>>((first, second))
+            };
+            match page.await {
+                Ok((first, second)) => {
+                    let favicon = fetcher.get(endpoint, use_tls, "/favicon.ico").await.ok();
+                    return Ok(Self::from_responses(
+                        endpoint,
+                        use_tls,
+                        &first,
+                        &second,
+                        favicon.as_ref(),
+                    ));
+                }
+                Err(e) => {
+                    last_error = Some(format!(
+                        "{}://{}: {}",
+                        if use_tls { "https" } else { "http" },
+                        endpoint,
+                        e
+                    ))
+                }
+            }
+        }
+        Err(last_error.unwrap_or_else(|| "no address to fetch from".to_string()))
+    }
+
+    /// Fetch the page at an `http://` or `https://` URL
+    ///
+    /// # Arguments
+    /// * `url` - Page to fetch; its host is used as Host header and TLS SNI
+    /// * `options` - Timeouts, headers and verification; the URL replaces
+    ///   the scheme, port and path
+    ///
+    /// # Returns
+    /// * `Ok(Baseline)` - The page answered
+    /// * `Err` - If the URL is malformed or the page could not be fetched
+    pub async fn fetch_url(
+        url: &str,
+        mut options: BaselineOptions,
+    ) -> Result<Self, Box<dyn std::error::Error>> {
+        let (scheme, rest) = if let Some(rest) = url.strip_prefix("https://") {
+            (Scheme::Https, rest)
+        } else if let Some(rest) = url.strip_prefix("http://") {
+            (Scheme::Http, rest)
+        } else {
+            return Err(format!("Invalid URL '{}': expected http:// or https://", url).into());
+        };
+        let (authority, path) = match rest.find('/') {
+            Some(i) => rest.split_at(i),
+            None => (rest, "/"),
+        };
+
+        // Split off the port, minding the colons of a bracketed IPv6 address
+        let (host, port) = match authority.rsplit_once(':') {
+            Some((host, port)) if !port.contains(']') => {
+                let port = port
+                    .parse::<u16>()
+                    .map_err(|_| format!("Invalid port in URL '{}'", url))?;
+                (host, Some(port))
+            }
+            _ => (authority, None),
+        };
+        let host = host.trim_start_matches('[').trim_end_matches(']');
+        if host.is_empty() {
+            return Err(format!("Invalid URL '{}': missing host", url).into());
+        }
+
+        options.scheme = scheme;
+        options.port = port;
+        options.path = path.to_string();
+        Self::fetch(host, &options).await
+    }
+
+    /// Record what two fetches of the page (and the favicon) agree on
+    fn from_responses(
+        endpoint: SocketAddr,
+        use_tls: bool,
+        first: &HttpResponse,
+        second: &HttpResponse,
+        favicon: Option<&HttpResponse>,
+    ) -> Self {
+        let cdn_server = first
+            .header("Server")
+            .filter(|server| {
+                let server = server.to_ascii_lowercase();
+                CDN_SERVERS.iter().any(|cdn| server.contains(cdn))
+            })
+            .map(str::to_string);
+
+        let headers = first
+            .headers
+            .iter()
+            .filter(|(name, value)| {
+                let lower = name.to_ascii_lowercase();
+                !GENERIC_HEADERS.contains(&lower.as_str())
+                    && !CDN_HEADER_PREFIXES
+                        .iter()
+                        .any(|prefix| lower.starts_with(prefix))
+                    && second.header(name) == Some(value.as_str())
+            })
+            .cloned()
+            .collect();
+
+        let body = first.body_text();
+        let stable = simhash::features(&body)
+            .intersection(&simhash::features(&second.body_text()))
+            .copied()
+            .collect::<Vec<_>>();
+        let simhash = (stable.len() >= MIN_FEATURES).then(|| simhash::simhash(&stable));
+
+        // Sites that answer every path with their HTML page have no favicon
+        let favicon_hash = favicon
+            .filter(|icon| icon.status == 200 && !icon.body.is_empty())
+            .filter(|icon| !icon.body.trim_ascii_start().starts_with(b"<"))
+            .map(|icon| matcher::favicon_hash(&icon.body));
+
+        Self {
+            endpoint,
+            scheme: if use_tls { "https" } else { "http" },
+            status: first.status,
+            title: matcher::page_title(&body).filter(|title| !title.is_empty()),
+            cdn_server,
+            headers,
+            simhash,
+            features: stable.len(),
+            favicon_hash,
+        }
+    }
+
+    /// Match rules that recognise the same site on another address
+    ///
+    /// # Arguments
+    /// * `with_body` - Include the title, simhash and favicon rules; leave them
+    ///   out for HEAD scans, whose responses have no body
+    /// * `min_similarity` - How alike bodies must be to the baseline's simhash
+    ///   (see [`SIMHASH_MIN_SIMILARITY`](crate::matcher::SIMHASH_MIN_SIMILARITY))
+    ///
+    /// # Returns
+    /// Every rule must match: the same status, the same site-specific
+    /// headers, not the CDN's `Server` header, and (with the body) either the
+    /// same title and a similar body, or the site's favicon, so scans of
+    /// `/favicon.ico` recognise the origin too.
+    pub fn matcher(&self, with_body: bool, min_similarity: f64) -> AndMatcher {
+        let mut rules: Vec<Box<dyn Matcher>> = vec![Box::new(StatusMatcher(
+            self.status
+                .to_string()
+                .parse()
+                .expect("a status code is a valid status set"),
+        ))];
+        for (name, value) in &self.headers {
+            rules.push(Box::new(HeaderMatcher::new(name, exact(value))));
+        }
+        if let Some(ref server) = self.cdn_server {
+            rules.push(Box::new(NotMatcher(Box::new(HeaderMatcher::new(
+                "Server",
+                exact(server),
+            )))));
+        }
+        if with_body {
+            let mut page: Vec<Box<dyn Matcher>> = Vec::new();
+            if let Some(ref title) = self.title {
+                page.push(Box::new(TitleMatcher(exact(title))));
+            }
+            if let Some(hash) = self.simhash {
+                page.push(Box::new(SimhashMatcher::new(hash, min_similarity)));
+            }
+            let page: Option<Box<dyn Matcher>> = match page.len() {
+                0 => None,
+                1 => page.pop(),
+                _ => Some(Box::new(AndMatcher(page))),
+            };
+            let favicon = self
+                .favicon_hash
+                .map(|hash| Box::new(FaviconHashMatcher(hash)) as Box<dyn Matcher>);
+            match (page, favicon) {
+                (Some(page), Some(favicon)) => rules.push(Box::new(OrMatcher(vec![page, favicon]))),
+                (page, favicon) => rules.extend(page.or(favicon)),
+            }
+        }
+        AndMatcher(rules)
+    }
+}
+
+/// Regex matching exactly the given text
+fn exact(text: &str) -> Regex {
+    Regex::new(&format!("^{}$", regex::escape(text))).expect("escaped text is a valid regex")
+}
+
+/// Sends the baseline requests
+struct Fetcher {
+    request_headers: String,
+    connector: tokio_rustls::TlsConnector,
+    server_name: tokio_rustls::rustls::pki_types::ServerName<'static>,
+    timeout: Duration,
+    max_body: usize,
+}
+
+impl Fetcher {
+    fn new(domain: &str, options: &BaselineOptions) -> Result<Self, Box<dyn std::error::Error>> {
+        let mut request_headers = format!("Host: {}\r\n", domain);
+        for header in &options.headers {
+            if !header.contains(':') {
+                return Err(format!(
+                    "Invalid header format: '{}'. Expected 'Header: Value'",
+                    header
+                )
+                .into());
+            }
+            request_headers.push_str(header);
+            request_headers.push_str("\r\n");
+        }
+        request_headers.push_str("Accept: */*\r\n");
+        request_headers.push_str("Connection: close\r\n");
+        request_headers.push_str("User-Agent: octointel/2.0\r\n\r\n");
+
+        Ok(Self {
+            request_headers,
+            connector: tls::build_connector(&options.verification)?,
+            server_name: tls::server_name(domain)?,
+            timeout: options.timeout,
+            max_body: options.max_body,
+        })
+    }
+
+    /// GET a path from an endpoint on a fresh connection
+    async fn get(
+        &self,
+        endpoint: SocketAddr,
+        use_tls: bool,
+        path: &str,
+    ) -> Result<HttpResponse, Box<dyn std::error::Error>> {
+        let stream = timeout(self.timeout, TcpStream::connect(endpoint))
+            .await
+            .map_err(|_| "connection timeout")??;
+        let mut stream = if use_tls {
+            let tls_stream = timeout(
+                self.timeout,
+                self.connector.connect(self.server_name.clone(), stream),
+            )
+            .await
+            .map_err(|_| "TLS handshake timeout")??;
+            MaybeTlsStream::Tls(Box::new(tls_stream))
+        } else {
+            MaybeTlsStream::Plain(stream)
+        };
+
+        let request = format!("GET {} HTTP/1.1\r\n{}", path, self.request_headers);
+        stream.write_all(request.as_bytes()).await?;
+        let deadline = Instant::now() + self.timeout;
+        Ok(http::read_response(&mut stream, false, self.max_body, deadline).await?)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::matcher::MatchInput;
+    use std::sync::atomic::{AtomicU64, Ordering};
+    use std::sync::Arc;
+    use tokio::io::AsyncReadExt;
+    use tokio::net::TcpListener;
+
+    const PAGE: &str = "<html><head><title>Example Shop</title></head><body>\
+        <h1>Welcome to the Example Shop</h1><p>Hand made goods shipped from our \
+        small workshop to your door, every week of the year.</p></body></html>";
+    const FAVICON: &[u8] = &[0, 0, 1, 0, 1, 0, 16, 16, 0, 0];
+
+    /// Serve the site on a local port, as a CDN edge (`server`) would
+    ///
+    /// Every response carries a stable `X-Site` header and a `X-Nonce`
+    /// header that changes on every request.
+    async fn serve_site(server: &'static str) -> SocketAddr {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let addr = listener.local_addr().unwrap();
+        let requests = Arc::new(AtomicU64::new(0));
+        tokio::spawn(async move {
+            while let Ok((mut socket, _)) = listener.accept().await {
+                let nonce = requests.fetch_add(1, Ordering::Relaxed);
+                tokio::spawn(async move {
+                    let mut request = Vec::new();
+                    let mut buf = [0u8; 1024];
+                    while !request.windows(4).any(|w| w == b"\r\n\r\n") {
+                        match socket.read(&mut buf).await {
+                            Ok(0) | Err(_) => return,
+                            Ok(n) => request.extend_from_slice(&buf[..n]),
+                        }
+                    }
+                    let body: &[u8] = if request.starts_with(b"GET /favicon.ico ") {
+                        FAVICON
+                    } else {
+                        PAGE.as_bytes()
+                    };
+                    let head = format!(
+                        "HTTP/1.1 200 OK\r\nServer: {}\r\nX-Site: shop\r\nX-Nonce: {}\r\n\
+                         Content-Length: {}\r\nConnection: close\r\n\r\n",
+                        server,
+                        nonce,
+                        body.len()
+                    );
+                    let _ = socket.write_all(head.as_bytes()).await;
+                    let _ = socket.write_all(body).await;
+                });
+            }
+        });
+        addr
+    }
+
+    fn options(endpoint: SocketAddr) -> BaselineOptions {
+        BaselineOptions {
+            address: Some(endpoint.ip()),
+            port: Some(endpoint.port()),
+            scheme: Scheme::Http,
+            timeout: Duration::from_secs(5),
+            ..BaselineOptions::default()
+        }
+    }
+
+    #[tokio::test]
+    async fn baseline_matches_origin_and_not_cdn() {
+        let cdn = serve_site("cloudflare").await;
+        let origin = serve_site("nginx").await;
+
+        let baseline = Baseline::fetch("shop.example", &options(cdn))
+            .await
+            .unwrap();
+        assert_eq!(baseline.status, 200);
+        assert_eq!(baseline.title.as_deref(), Some("Example Shop"));
+        assert_eq!(baseline.cdn_server.as_deref(), Some("cloudflare"));
+        assert_eq!(
+            baseline.headers,
+            vec![("X-Site".to_string(), "shop".to_string())]
+        );
+        assert!(baseline.simhash.is_some());
+        assert_eq!(baseline.favicon_hash, Some(matcher::favicon_hash(FAVICON)));
+
+        let rules = baseline.matcher(true, matcher::SIMHASH_MIN_SIMILARITY);
+        let described = rules.describe();
+        assert!(described.contains("header:X-Site~"), "{}", described);
+        assert!(described.contains("title~"), "{}", described);
+        assert!(described.contains("simhash="), "{}", described);
+        assert!(described.contains(" or favicon-hash="), "{}", described);
+        assert!(!described.contains("X-Nonce"), "{}", described);
+
+        let fetcher = Fetcher::new("shop.example", &options(origin)).unwrap();
+        let matches = |endpoint, path| {
+            let fetcher = &fetcher;
+            let rules = &rules;
+            async move {
+                let response = fetcher.get(endpoint, false, path).await.unwrap();
+                rules
+                    .evaluate(&MatchInput {
+                        response: &response,
+                        certificate: None,
+                    })
+                    .is_some()
+            }
+        };
+        assert!(matches(origin, "/").await);
+        assert!(!matches(cdn, "/").await);
+        assert!(matches(origin, "/favicon.ico").await);
+        assert!(!matches(cdn, "/favicon.ico").await);
+    }
+
+    /// Serve the page once, then drop every further connection unanswered
+    async fn serve_once() -> SocketAddr {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let addr = listener.local_addr().unwrap();
+        tokio::spawn(async move {
+            let Ok((mut socket, _)) = listener.accept().await else {
+                return;
+            };
+            let head = format!(
+                "HTTP/1.1 200 OK\r\nContent-Length: {}\r\nConnection: close\r\n\r\n",
+                PAGE.len()
+            );
+            let _ = socket.write_all(head.as_bytes()).await;
+            let _ = socket.write_all(PAGE.as_bytes()).await;
+            drop(socket);
+            while let Ok((socket, _)) = listener.accept().await {
+                drop(socket);
+            }
+        });
+        addr
+    }
+
+    #[tokio::test]
+    async fn failed_second_fetch_moves_on_to_the_next_endpoint() {
+        let flaky = serve_once().await;
+        let site = serve_site("nginx").await;
+        let fetcher = Fetcher::new("shop.example", &options(site)).unwrap();
+
+        let baseline = Baseline::fetch_first(&fetcher, [(flaky, false), (site, false)], "/")
+            .await
+            .unwrap();
+        assert_eq!(baseline.endpoint, site);
+
+        let flaky = serve_once().await;
+        let err = Baseline::fetch_first(&fetcher, [(flaky, false)], "/")
+            .await
+            .unwrap_err();
+        assert!(err.starts_with(&format!("http://{}:", flaky)), "{}", err);
+    }
+}
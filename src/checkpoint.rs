@@ -0,0 +1,126 @@
+use crate::ScanHit;
+use serde::{Deserialize, Serialize};
+use std::fs;
+use std::path::Path;
+
+/// Checkpoint format version, bumped whenever the layout changes
+const CHECKPOINT_VERSION: u32 = 1;
+
+/// Saved state of an interrupted scan
+///
+/// `position` counts (ip, port) targets across all `ranges` in order, so
+/// resuming skips exactly the targets that were already fully scanned.
+#[derive(Debug, Serialize, Deserialize)]
+pub struct Checkpoint {
+    pub version: u32,
+    /// Hash of the match-relevant configuration (see `ScannerConfig::fingerprint`)
+    pub config_hash: String,
+    /// Target domain, kept for humans reading the file
+    pub domain: String,
+    /// Every range of the original scan, in scan order
+    pub ranges: Vec<String>,
+    /// Number of targets (across all ranges) already scanned
+    pub position: u64,
+    /// Hits found before the interruption
+    pub hits: Vec<ScanHit>,
+}
+
+impl Checkpoint {
+    pub fn new(
+        config_hash: String,
+        domain: String,
+        ranges: Vec<String>,
+        position: u64,
+        hits: Vec<ScanHit>,
+    ) -> Self {
+        Self {
+            version: CHECKPOINT_VERSION,
+            config_hash,
+            domain,
+            ranges,
+            position,
+            hits,
+        }
+    }
+
+    /// Load a checkpoint written by [`Checkpoint::save`]
+    pub fn load(path: &Path) -> Result<Self, Box<dyn std::error::Error>> {
+        let content = fs::read_to_string(path)?;
+        let checkpoint: Checkpoint = serde_json::from_str(&content)?;
+        if checkpoint.version != CHECKPOINT_VERSION {
+            return Err(format!(
+                "Unsupported checkpoint version {} (expected {})",
+                checkpoint.version, CHECKPOINT_VERSION
+            )
+            .into());
+        }
+        Ok(checkpoint)
+    }
+
+    /// Write the checkpoint atomically (temp file + rename)
+    pub fn save(&self, path: &Path) -> Result<(), Box<dyn std::error::Error>> {
+        let tmp = path.with_extension("tmp");
+        fs::write(&tmp, serde_json::to_string_pretty(self)?)?;
+        fs::rename(&tmp, path)?;
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::path::PathBuf;
+
+    fn temp_path(name: &str) -> PathBuf {
+        std::env::temp_dir().join(format!(
+            "octointel-checkpoint-{}-{}.json",
+            std::process::id(),
+            name
+        ))
+    }
+
+    #[test]
+    fn saved_checkpoint_loads_back() {
+        let path = temp_path("round-trip");
+        let ranges = vec!["10.0.0.0/30".to_string(), "10.0.1.0/31".to_string()];
+        Checkpoint::new(
+            "abc123".to_string(),
+            "example.com".to_string(),
+            ranges,
+            5,
+            Vec::new(),
+        )
+        .save(&path)
+        .unwrap();
+        let loaded = Checkpoint::load(&path);
+        fs::remove_file(&path).unwrap();
+
+        let loaded = loaded.unwrap();
+        assert_eq!(loaded.version, CHECKPOINT_VERSION);
+        assert_eq!(loaded.config_hash, "abc123");
+        assert_eq!(loaded.domain, "example.com");
+        assert_eq!(loaded.ranges, ["10.0.0.0/30", "10.0.1.0/31"]);
+        assert_eq!(loaded.position, 5);
+        assert!(loaded.hits.is_empty());
+        assert!(!path.with_extension("tmp").exists());
+    }
+
+    #[test]
+    fn other_versions_are_rejected() {
+        let path = temp_path("version");
+        let mut checkpoint = Checkpoint::new(
+            String::new(),
+            "example.com".to_string(),
+            Vec::new(),
+            0,
+            Vec::new(),
+        );
+        checkpoint.version = CHECKPOINT_VERSION + 1;
+        checkpoint.save(&path).unwrap();
+        let result = Checkpoint::load(&path);
+        fs::remove_file(&path).unwrap();
+
+        let err = result.unwrap_err().to_string();
+        assert!(err.starts_with("Unsupported checkpoint version"), "{}", err);
+    }
+}
@@ -0,0 +1,114 @@
+use clap::ValueEnum;
+use std::fmt;
+use std::io;
+use std::pin::Pin;
+use std::task::{Context, Poll};
+use tokio::io::{AsyncRead, AsyncWrite, ReadBuf};
+use tokio::net::TcpStream;
+use tokio_rustls::client::TlsStream;
+
+/// Protocol spoken on scanned ports
+#[derive(Debug, Clone, Copy, PartialEq, Eq, ValueEnum)]
+pub enum Scheme {
+    /// Port 80 is plaintext, 443 is TLS, any other port is detected by offering a TLS handshake
+    Auto,
+    /// Plaintext HTTP on every port
+    Http,
+    /// HTTPS on every port (SNI is set to the target domain)
+    Https,
+}
+
+impl Scheme {
+    /// Whether `port` is known to speak TLS under this scheme
+    ///
+    /// # Returns
+    /// * `Some(tls)` - The port is plaintext (`false`) or TLS (`true`)
+    /// * `None` - Has to be detected per host (auto mode on a non-standard port)
+    pub fn tls_for_port(self, port: u16) -> Option<bool> {
+        match (self, port) {
+            (Scheme::Http, _) => Some(false),
+            (Scheme::Https, _) => Some(true),
+            (Scheme::Auto, 80) => Some(false),
+            (Scheme::Auto, 443) => Some(true),
+            (Scheme::Auto, _) => None,
+        }
+    }
+}
+
+impl fmt::Display for Scheme {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let name = self.to_possible_value().map(|v| v.get_name().to_string());
+        f.write_str(name.as_deref().unwrap_or("unknown"))
+    }
+}
+
+/// Connection to a scanned host, either plaintext or wrapped in TLS
+///
+/// Lets the HTTP exchange run over the same code path for both schemes.
+pub enum MaybeTlsStream {
+    Plain(TcpStream),
+    Tls(Box<TlsStream<TcpStream>>),
+}
+
+impl MaybeTlsStream {
+    /// URL scheme of the connection: "http" or "https"
+    pub fn scheme(&self) -> &'static str {
+        match self {
+            MaybeTlsStream::Plain(_) => "http",
+            MaybeTlsStream::Tls(_) => "https",
+        }
+    }
+
+    /// DER-encoded leaf certificate presented by the peer (TLS connections only)
+    pub fn peer_certificate(&self) -> Option<&[u8]> {
+        match self {
+            MaybeTlsStream::Plain(_) => None,
+            MaybeTlsStream::Tls(stream) => stream
+                .get_ref()
+                .1
+                .peer_certificates()?
+                .first()
+                .map(|cert| cert.as_ref()),
+        }
+    }
+}
+
+impl AsyncRead for MaybeTlsStream {
+    fn poll_read(
+        self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+        buf: &mut ReadBuf<'_>,
+    ) -> Poll<io::Result<()>> {
+        match self.get_mut() {
+            MaybeTlsStream::Plain(s) => Pin::new(s).poll_read(cx, buf),
+            MaybeTlsStream::Tls(s) => Pin::new(s).poll_read(cx, buf),
+        }
+    }
+}
+
+impl AsyncWrite for MaybeTlsStream {
+    fn poll_write(
+        self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+        buf: &[u8],
+    ) -> Poll<io::Result<usize>> {
+        match self.get_mut() {
+            MaybeTlsStream::Plain(s) => Pin::new(s).poll_write(cx, buf),
+            MaybeTlsStream::Tls(s) => Pin::new(s).poll_write(cx, buf),
+        }
+    }
+
+    fn poll_flush(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<io::Result<()>> {
+        match self.get_mut() {
+            MaybeTlsStream::Plain(s) => Pin::new(s).poll_flush(cx),
+            MaybeTlsStream::Tls(s) => Pin::new(s).poll_flush(cx),
+        }
+    }
+
+    fn poll_shutdown(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<io::Result<()>> {
+        match self.get_mut() {
+            MaybeTlsStream::Plain(s) => Pin::new(s).poll_shutdown(cx),
+            MaybeTlsStream::Tls(s) => Pin::new(s).poll_shutdown(cx),
+        }
+    }
+}
@@ -0,0 +1,51 @@
+use std::fs;
+use std::io::{BufWriter, Write};
+use std::net::SocketAddr;
+use std::path::Path;
+
+/// Write open endpoints found by discovery, one `ip:port` per line
+///
+/// IPv6 endpoints are written in bracket form (`[2001:db8::1]:443`), so
+/// the file round-trips through [`load_endpoints`].
+pub fn save_endpoints(path: &Path, endpoints: &[SocketAddr]) -> std::io::Result<()> {
+    let mut file = BufWriter::new(fs::File::create(path)?);
+    writeln!(file, "# Open endpoints found by octointel discovery")?;
+    for endpoint in endpoints {
+        writeln!(file, "{}", endpoint)?;
+    }
+    file.flush()
+}
+
+/// Load endpoints written by [`save_endpoints`]
+///
+/// # Returns
+/// * `Ok(Vec<SocketAddr>)` - Endpoints in file order
+/// * `Err` - If the file can't be read, a line is not `ip:port`, or it is empty
+///
+/// Empty lines and lines starting with `#` are ignored.
+pub fn load_endpoints(path: &Path) -> Result<Vec<SocketAddr>, Box<dyn std::error::Error>> {
+    let content = fs::read_to_string(path)?;
+    let mut endpoints = Vec::new();
+
+    for (line_num, line) in content.lines().enumerate() {
+        let line = line.trim();
+        if line.is_empty() || line.starts_with('#') {
+            continue;
+        }
+        let endpoint = line.parse::<SocketAddr>().map_err(|e| {
+            format!(
+                "Invalid endpoint on line {}: '{}' ({})",
+                line_num + 1,
+                line,
+                e
+            )
+        })?;
+        endpoints.push(endpoint);
+    }
+
+    if endpoints.is_empty() {
+        return Err("No endpoints found in file".into());
+    }
+
+    Ok(endpoints)
+}
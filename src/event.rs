@@ -0,0 +1,99 @@
+use crate::ranges::{check_range, RangeTracker};
+use crate::stats::ScanStats;
+use crate::ScanHit;
+use ipnetwork::IpNetwork;
+use std::net::SocketAddr;
+use std::sync::Arc;
+use std::time::Duration;
+
+/// What a scan is working on
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Phase {
+    /// TCP connect only, to find open endpoints (first phase of a two-phase scan)
+    Discovery,
+    /// Full probes: HTTP requests or certificate checks
+    Probe,
+}
+
+/// Targets of a scan
+#[derive(Debug, Clone)]
+pub enum Targets {
+    /// Every address of the ranges on every configured port, range after range
+    ///
+    /// IPv6 ranges must be /112 or smaller; [`Scanner::scan`](crate::Scanner::scan)
+    /// fails with [`ScanEvent::Failed`] otherwise.
+    ///
+    /// `skip` leading targets are treated as already scanned (resuming from
+    /// [`ScanSummary::position`] of an earlier, cancelled scan).
+    Ranges { ranges: Vec<IpNetwork>, skip: u64 },
+    /// Explicit endpoints, e.g. saved by an earlier discovery run
+    Endpoints(Vec<SocketAddr>),
+}
+
+impl Targets {
+    /// Check that every range is small enough to sweep (see [`check_range`])
+    pub fn validate(&self) -> Result<(), String> {
+        match self {
+            Targets::Ranges { ranges, .. } => ranges.iter().try_for_each(check_range),
+            Targets::Endpoints(_) => Ok(()),
+        }
+    }
+}
+
+/// Something that happened during a scan, delivered in order
+#[derive(Debug, Clone)]
+pub enum ScanEvent {
+    /// A phase started with `total` targets, the first `skip` of them already done
+    PhaseStarted { phase: Phase, total: u64, skip: u64 },
+    /// Periodic progress of the current phase
+    ///
+    /// `status` is a short human-readable line: finished ranges and the
+    /// adaptive concurrency controller's latest decision.
+    Progress {
+        phase: Phase,
+        done: u64,
+        total: u64,
+        status: String,
+    },
+    /// Every target of a range has been scanned; `found` counts its hits
+    /// (open endpoints, during discovery)
+    RangeFinished {
+        phase: Phase,
+        network: IpNetwork,
+        total: u64,
+        found: u64,
+    },
+    /// Discovery is over; probing continues on these open endpoints (sorted)
+    Discovered(Vec<SocketAddr>),
+    /// A backend matched every rule
+    Hit(ScanHit),
+    /// A target failed (reported only when `verbose` is set)
+    Error { target: SocketAddr, message: String },
+    /// Diagnostic detail about a target (reported only when `verbose` is set)
+    Debug { target: SocketAddr, message: String },
+    /// The scan is over; the last event unless the scan task panicked
+    Finished(ScanSummary),
+    /// The scan could not start (invalid targets) or its task panicked;
+    /// the last event instead of `Finished`
+    Failed(String),
+}
+
+/// Outcome of a whole scan, carried by [`ScanEvent::Finished`]
+#[derive(Debug, Clone)]
+pub struct ScanSummary {
+    /// Every hit, in the order found
+    pub hits: Vec<ScanHit>,
+    /// Targets fully scanned, including the initial `skip` of a range scan;
+    /// pass it back as `skip` to resume a cancelled scan
+    pub position: u64,
+    /// Whether the scan was stopped through its cancellation token
+    pub cancelled: bool,
+    /// Whether the scan stopped because `max_finds` hits were found
+    pub limit_reached: bool,
+    /// Connection counters of the whole scan
+    pub stats: Arc<ScanStats>,
+    /// Per-range counters (range scans only)
+    pub ranges: Option<Arc<RangeTracker>>,
+    /// Wall-clock duration of the scan
+    pub elapsed: Duration,
+}
@@ -0,0 +1,579 @@
+use std::borrow::Cow;
+use std::fmt;
+use std::io;
+use std::ops::RangeInclusive;
+use std::str::FromStr;
+use tokio::io::{AsyncRead, AsyncReadExt};
+use tokio::time::{timeout_at, Instant};
+
+/// Upper bound on the status line plus headers, to avoid buffering garbage forever
+const MAX_HEAD_SIZE: usize = 64 * 1024;
+
+/// Upper bound on a chunk size line (size plus extensions), like `MAX_HEAD_SIZE` for the head
+const MAX_CHUNK_LINE: usize = 4096;
+
+/// Maximum number of headers parsed from a single response
+const MAX_HEADERS: usize = 100;
+
+/// Size of each individual read from the socket
+const READ_CHUNK: usize = 4096;
+
+/// Parsed HTTP/1.x response
+///
+/// Built by [`read_response`] and shared by every matcher, so status, header
+/// and body checks all look at the same structured view of the reply.
+#[derive(Debug, Clone)]
+pub struct HttpResponse {
+    /// Numeric status code from the status line
+    pub status: u16,
+    /// Reason phrase from the status line (may be empty)
+    pub reason: String,
+    /// Headers in the order received, names as sent by the server
+    pub headers: Vec<(String, String)>,
+    /// Decoded body (de-chunked), capped at the configured body limit
+    pub body: Vec<u8>,
+    /// Whether the body was cut short by the body limit, the deadline, or the connection closing early
+    pub truncated: bool,
+}
+
+impl HttpResponse {
+    /// Value of the first header with the given name (case-insensitive)
+    pub fn header(&self, name: &str) -> Option<&str> {
+        self.headers
+            .iter()
+            .find(|(n, _)| n.eq_ignore_ascii_case(name))
+            .map(|(_, v)| v.as_str())
+    }
+
+    /// Status line and headers rendered back as text
+    pub fn head_text(&self) -> String {
+        let mut head = format!("HTTP/1.1 {} {}\r\n", self.status, self.reason);
+        for (name, value) in &self.headers {
+            head.push_str(name);
+            head.push_str(": ");
+            head.push_str(value);
+            head.push_str("\r\n");
+        }
+        head
+    }
+
+    /// Body decoded as UTF-8 (invalid sequences replaced)
+    pub fn body_text(&self) -> Cow<'_, str> {
+        String::from_utf8_lossy(&self.body)
+    }
+}
+
+/// Set of HTTP status codes a response may have to count as a match
+///
+/// Parsed from a comma-separated list of exact codes (`200`), inclusive
+/// ranges (`301-302`) and class wildcards (`2xx`).
+#[derive(Debug, Clone)]
+pub struct StatusSet {
+    ranges: Vec<RangeInclusive<u16>>,
+    spec: String,
+}
+
+impl StatusSet {
+    /// Whether the given status code is in the set
+    pub fn contains(&self, status: u16) -> bool {
+        self.ranges.iter().any(|range| range.contains(&status))
+    }
+}
+
+impl FromStr for StatusSet {
+    type Err = String;
+
+    fn from_str(spec: &str) -> Result<Self, Self::Err> {
+        let parse_code = |code: &str| -> Result<u16, String> {
+            match code.trim().parse::<u16>() {
+                Ok(n) if (100..=599).contains(&n) => Ok(n),
+                _ => Err(format!("invalid status code '{}'", code.trim())),
+            }
+        };
+
+        let mut ranges = Vec::new();
+        for part in spec.split(',').map(str::trim).filter(|p| !p.is_empty()) {
+            let lower = part.to_ascii_lowercase();
+            let range = if let Some(class) = lower.strip_suffix("xx") {
+                match class.parse::<u16>() {
+                    Ok(c) if (1..=5).contains(&c) => c * 100..=c * 100 + 99,
+                    _ => return Err(format!("invalid status class '{}'", part)),
+                }
+            } else if let Some((start, end)) = part.split_once('-') {
+                let (start, end) = (parse_code(start)?, parse_code(end)?);
+                if start > end {
+                    return Err(format!("invalid status range '{}'", part));
+                }
+                start..=end
+            } else {
+                let code = parse_code(part)?;
+                code..=code
+            };
+            ranges.push(range);
+        }
+
+        if ranges.is_empty() {
+            return Err("no status codes given".to_string());
+        }
+
+        Ok(Self {
+            ranges,
+            spec: spec.split(',').map(str::trim).collect::<Vec<_>>().join(","),
+        })
+    }
+}
+
+impl fmt::Display for StatusSet {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(&self.spec)
+    }
+}
+
+/// Read and parse an HTTP/1.x response from a stream
+///
+/// # Arguments
+/// * `stream` - Connected stream the request was written to
+/// * `head_request` - Whether the request was HEAD (response carries no body)
+/// * `max_body` - Maximum number of body bytes to keep
+/// * `deadline` - Point in time after which no further reads are attempted
+///
+/// # Returns
+/// * `Ok(HttpResponse)` - Parsed response (body possibly truncated)
+/// * `Err` - If the connection failed, closed, or hit the deadline before a
+///   full status line and headers arrived, or the response is not valid HTTP
+///
+/// # Behavior
+/// - Keeps reading until the headers are complete, skipping interim 1xx responses
+/// - Frames the body by `Transfer-Encoding: chunked`, `Content-Length`, or EOF
+/// - Stops as soon as `max_body` bytes of body have been collected
+/// - If the deadline passes mid-body, returns what has arrived so far
+pub async fn read_response<R>(
+    stream: &mut R,
+    head_request: bool,
+    max_body: usize,
+    deadline: Instant,
+) -> io::Result<HttpResponse>
+where
+    R: AsyncRead + Unpin,
+{
+    let mut reader = Reader {
+        stream,
+        deadline,
+        timed_out: false,
+    };
+    let mut buf = Vec::with_capacity(READ_CHUNK);
+
+    let (mut response, head_len) = loop {
+        if let Some(parsed) = parse_head(&buf)? {
+            let (response, head_len) = parsed;
+            // Interim responses (100 Continue, 103 Early Hints) precede the real one
+            if (100..200).contains(&response.status) && response.status != 101 {
+                buf.drain(..head_len);
+                continue;
+            }
+            break (response, head_len);
+        }
+
+        if buf.len() > MAX_HEAD_SIZE {
+            return Err(invalid_data("response headers too large"));
+        }
+        if !reader.fill(&mut buf).await? {
+            return Err(if reader.timed_out {
+                io::Error::new(
+                    io::ErrorKind::TimedOut,
+                    "timed out waiting for response headers",
+                )
+            } else {
+                io::Error::new(
+                    io::ErrorKind::UnexpectedEof,
+                    "connection closed before response headers",
+                )
+            });
+        }
+    };
+
+    buf.drain(..head_len);
+
+    let has_body = !head_request
+        && !(100..200).contains(&response.status)
+        && response.status != 204
+        && response.status != 304;
+
+    if !has_body || max_body == 0 {
+        return Ok(response);
+    }
+
+    let chunked = response
+        .header("Transfer-Encoding")
+        .is_some_and(|te| te.to_ascii_lowercase().contains("chunked"));
+
+    if chunked {
+        read_chunked_body(&mut reader, buf, max_body, &mut response).await?;
+    } else {
+        let content_length = match response.header("Content-Length") {
+            Some(value) => Some(
+                value
+                    .trim()
+                    .parse::<usize>()
+                    .map_err(|_| invalid_data("invalid Content-Length"))?,
+            ),
+            None => None,
+        };
+        let wanted = content_length.map_or(max_body, |len| len.min(max_body));
+
+        while buf.len() < wanted {
+            if !reader.fill(&mut buf).await? {
+                // Without Content-Length, EOF is the normal end of the body
+                response.truncated = content_length.is_some() || reader.timed_out;
+                break;
+            }
+        }
+
+        if buf.len() > wanted || content_length.is_some_and(|len| len > max_body) {
+            response.truncated = true;
+        }
+        buf.truncate(wanted);
+        response.body = buf;
+    }
+
+    Ok(response)
+}
+
+/// Decode a `Transfer-Encoding: chunked` body into `response.body`
+async fn read_chunked_body<R>(
+    reader: &mut Reader<'_, R>,
+    mut buf: Vec<u8>,
+    max_body: usize,
+    response: &mut HttpResponse,
+) -> io::Result<()>
+where
+    R: AsyncRead + Unpin,
+{
+    loop {
+        // Chunk size line: hex size, optional ";extensions", CRLF
+        let line_end = loop {
+            if let Some(pos) = buf.windows(2).position(|w| w == b"\r\n") {
+                break pos;
+            }
+            if buf.len() > MAX_CHUNK_LINE {
+                return Err(invalid_data("chunk size line too long"));
+            }
+            if !reader.fill(&mut buf).await? {
+                response.truncated = true;
+                return Ok(());
+            }
+        };
+        if line_end > MAX_CHUNK_LINE {
+            return Err(invalid_data("chunk size line too long"));
+        }
+
+        let line = String::from_utf8_lossy(&buf[..line_end]);
+        let size_str = line.split(';').next().unwrap_or("").trim();
+        let size =
+            usize::from_str_radix(size_str, 16).map_err(|_| invalid_data("invalid chunk size"))?;
+        buf.drain(..line_end + 2);
+
+        if size == 0 {
+            return Ok(());
+        }
+
+        // Only buffer as much of the chunk as still fits under the limit
+        let room = max_body - response.body.len();
+        let wanted = size.min(room);
+        while buf.len() < wanted {
+            if !reader.fill(&mut buf).await? {
+                response.body.extend_from_slice(&buf);
+                response.truncated = true;
+                return Ok(());
+            }
+        }
+        response.body.extend_from_slice(&buf[..wanted]);
+
+        if wanted < size || response.body.len() >= max_body {
+            response.truncated = true;
+            return Ok(());
+        }
+
+        // Chunk data is followed by CRLF
+        while buf.len() < size + 2 {
+            if !reader.fill(&mut buf).await? {
+                return Ok(());
+            }
+        }
+        buf.drain(..size + 2);
+    }
+}
+
+/// Try to parse a status line and headers from the start of `buf`
+///
+/// Returns `Ok(None)` while the head is still incomplete.
+fn parse_head(buf: &[u8]) -> io::Result<Option<(HttpResponse, usize)>> {
+    let mut headers = [httparse::EMPTY_HEADER; MAX_HEADERS];
+    let mut parsed = httparse::Response::new(&mut headers);
+
+    match parsed.parse(buf) {
+        Ok(httparse::Status::Complete(head_len)) => {
+            let response = HttpResponse {
+                status: parsed.code.unwrap_or(0),
+                reason: parsed.reason.unwrap_or("").to_string(),
+                headers: parsed
+                    .headers
+                    .iter()
+                    .map(|h| {
+                        (
+                            h.name.to_string(),
+                            String::from_utf8_lossy(h.value).into_owned(),
+                        )
+                    })
+                    .collect(),
+                body: Vec::new(),
+                truncated: false,
+            };
+            Ok(Some((response, head_len)))
+        }
+        Ok(httparse::Status::Partial) => Ok(None),
+        Err(e) => Err(invalid_data(&format!("malformed HTTP response: {}", e))),
+    }
+}
+
+/// Stream wrapper that bounds every read by a shared deadline
+struct Reader<'a, R> {
+    stream: &'a mut R,
+    deadline: Instant,
+    timed_out: bool,
+}
+
+impl<R> Reader<'_, R>
+where
+    R: AsyncRead + Unpin,
+{
+    /// Append one read's worth of data to `buf`
+    ///
+    /// Returns `false` on EOF or once the deadline has passed (`timed_out` is set).
+    async fn fill(&mut self, buf: &mut Vec<u8>) -> io::Result<bool> {
+        let mut chunk = [0u8; READ_CHUNK];
+        let n = match timeout_at(self.deadline, self.stream.read(&mut chunk)).await {
+            Ok(Ok(n)) => n,
+            // Many servers close TLS connections without close_notify; treat it as EOF
+            Ok(Err(e)) if e.kind() == io::ErrorKind::UnexpectedEof => 0,
+            Ok(Err(e)) => return Err(e),
+            Err(_) => {
+                self.timed_out = true;
+                0
+            }
+        };
+        buf.extend_from_slice(&chunk[..n]);
+        Ok(n > 0)
+    }
+}
+
+fn invalid_data(msg: &str) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidData, msg.to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::collections::VecDeque;
+    use std::pin::Pin;
+    use std::task::{Context, Poll};
+    use std::time::Duration;
+    use tokio::io::ReadBuf;
+
+    /// In-memory stream that hands out one piece per read, then EOF
+    struct Pieces(VecDeque<Vec<u8>>);
+
+    impl AsyncRead for Pieces {
+        fn poll_read(
+            mut self: Pin<&mut Self>,
+            _cx: &mut Context<'_>,
+            buf: &mut ReadBuf<'_>,
+        ) -> Poll<io::Result<()>> {
+            if let Some(mut piece) = self.0.pop_front() {
+                let n = piece.len().min(buf.remaining());
+                buf.put_slice(&piece[..n]);
+                if n < piece.len() {
+                    self.0.push_front(piece.split_off(n));
+                }
+            }
+            Poll::Ready(Ok(()))
+        }
+    }
+
+    async fn read(pieces: &[&str], max_body: usize) -> io::Result<HttpResponse> {
+        let mut stream = Pieces(pieces.iter().map(|p| p.as_bytes().to_vec()).collect());
+        let deadline = Instant::now() + Duration::from_secs(5);
+        read_response(&mut stream, false, max_body, deadline).await
+    }
+
+    #[tokio::test]
+    async fn chunked_body_with_extensions_and_trailers() {
+        let response = read(
+            &["HTTP/1.1 200 OK\r\nTransfer-Encoding: chunked\r\n\r\n\
+               5;name=value\r\nhello\r\n\
+               7\r\n, world\r\n\
+               0\r\nX-Trailer: yes\r\n\r\n"],
+            1024,
+        )
+        .await
+        .unwrap();
+        assert_eq!(response.status, 200);
+        assert_eq!(response.body, b"hello, world");
+        assert!(!response.truncated);
+    }
+
+    #[tokio::test]
+    async fn chunked_body_split_across_reads() {
+        let response = read(
+            &[
+                "HTTP/1.1 200 OK\r\nTransfer-Encoding: chunked\r\n\r\n5\r",
+                "\nhel",
+                "lo\r\n0\r\n\r\n",
+            ],
+            1024,
+        )
+        .await
+        .unwrap();
+        assert_eq!(response.body, b"hello");
+        assert!(!response.truncated);
+    }
+
+    #[tokio::test]
+    async fn truncated_chunk_keeps_what_arrived() {
+        let response = read(
+            &["HTTP/1.1 200 OK\r\nTransfer-Encoding: chunked\r\n\r\na\r\nabcd"],
+            1024,
+        )
+        .await
+        .unwrap();
+        assert_eq!(response.body, b"abcd");
+        assert!(response.truncated);
+    }
+
+    #[tokio::test]
+    async fn invalid_chunk_size_is_an_error() {
+        let err = read(
+            &["HTTP/1.1 200 OK\r\nTransfer-Encoding: chunked\r\n\r\nzz\r\n"],
+            1024,
+        )
+        .await
+        .unwrap_err();
+        assert_eq!(err.kind(), io::ErrorKind::InvalidData);
+    }
+
+    #[tokio::test]
+    async fn overlong_chunk_size_line_is_an_error() {
+        let head = "HTTP/1.1 200 OK\r\nTransfer-Encoding: chunked\r\n\r\n";
+        let extension = format!("5;{}\r\nhello\r\n0\r\n\r\n", "x".repeat(MAX_CHUNK_LINE));
+        let err = read(&[head, &extension], 1024).await.unwrap_err();
+        assert_eq!(err.kind(), io::ErrorKind::InvalidData);
+
+        // A line that never ends fails once it passes the limit, not at the deadline
+        let endless = "f".repeat(READ_CHUNK);
+        let mut pieces = vec![head];
+        pieces.extend(std::iter::repeat_n(endless.as_str(), 4));
+        let err = read(&pieces, 1024).await.unwrap_err();
+        assert_eq!(err.kind(), io::ErrorKind::InvalidData);
+    }
+
+    #[tokio::test]
+    async fn chunked_body_is_capped_at_max_body() {
+        let response = read(
+            &["HTTP/1.1 200 OK\r\nTransfer-Encoding: chunked\r\n\r\n8\r\nabcdefgh\r\n0\r\n\r\n"],
+            5,
+        )
+        .await
+        .unwrap();
+        assert_eq!(response.body, b"abcde");
+        assert!(response.truncated);
+    }
+
+    #[tokio::test]
+    async fn content_length_beyond_max_body_is_truncated() {
+        let response = read(
+            &["HTTP/1.1 200 OK\r\nContent-Length: 100\r\n\r\n0123456789abcdef"],
+            10,
+        )
+        .await
+        .unwrap();
+        assert_eq!(response.body, b"0123456789");
+        assert!(response.truncated);
+    }
+
+    #[tokio::test]
+    async fn short_content_length_body_is_truncated() {
+        let response = read(&["HTTP/1.1 200 OK\r\nContent-Length: 10\r\n\r\nabc"], 1024)
+            .await
+            .unwrap();
+        assert_eq!(response.body, b"abc");
+        assert!(response.truncated);
+    }
+
+    #[tokio::test]
+    async fn headers_split_across_reads() {
+        let response = read(
+            &[
+                "HTTP/1.1 2",
+                "00 OK\r\nServer: ng",
+                "inx\r\nContent-Le",
+                "ngth: 5\r\n\r",
+                "\nhel",
+                "lo",
+            ],
+            1024,
+        )
+        .await
+        .unwrap();
+        assert_eq!(response.status, 200);
+        assert_eq!(response.reason, "OK");
+        assert_eq!(response.header("server"), Some("nginx"));
+        assert_eq!(response.body, b"hello");
+        assert!(!response.truncated);
+    }
+
+    #[tokio::test]
+    async fn interim_responses_are_skipped() {
+        let response = read(
+            &["HTTP/1.1 100 Continue\r\n\r\nHTTP/1.1 202 Accepted\r\nContent-Length: 0\r\n\r\n"],
+            1024,
+        )
+        .await
+        .unwrap();
+        assert_eq!(response.status, 202);
+    }
+
+    #[tokio::test]
+    async fn incomplete_head_is_an_error() {
+        let err = read(&["HTTP/1.1 200 OK\r\nServer: nginx\r\n"], 1024)
+            .await
+            .unwrap_err();
+        assert_eq!(err.kind(), io::ErrorKind::UnexpectedEof);
+
+        let err = read(&["SSH-2.0-OpenSSH_9.6\r\n\r\n"], 1024)
+            .await
+            .unwrap_err();
+        assert_eq!(err.kind(), io::ErrorKind::InvalidData);
+    }
+
+    #[test]
+    fn status_set_parses_codes_ranges_and_classes() {
+        let set: StatusSet = "200, 3xx,400-404".parse().unwrap();
+        assert_eq!(set.to_string(), "200,3xx,400-404");
+        for status in [200, 300, 302, 399, 400, 403, 404] {
+            assert!(set.contains(status), "{}", status);
+        }
+        for status in [201, 299, 405, 500] {
+            assert!(!set.contains(status), "{}", status);
+        }
+        assert!("2XX".parse::<StatusSet>().unwrap().contains(204));
+    }
+
+    #[test]
+    fn status_set_rejects_invalid_input() {
+        for spec in [
+            "", ",", "abc", "99", "600", "6xx", "0xx", "404-400", "200-", "2x",
+        ] {
+            assert!(spec.parse::<StatusSet>().is_err(), "'{}' accepted", spec);
+        }
+    }
+}
@@ -0,0 +1,60 @@
+//! OctoIntel: find the backend servers hiding behind CDNs and reverse proxies
+//!
+//! The `octointel` command line tool is a thin front end over this crate.
+//! Build a [`ScannerConfig`], create a [`Scanner`] and consume the stream of
+//! [`ScanEvent`]s returned by [`Scanner::scan`]:
+//!
+//! ```no_run
+//! use futures::StreamExt;
+//! use octointel::{CancellationToken, ScanEvent, Scanner, ScannerConfig, Targets};
+//!
+//! # async fn example() -> Result<(), Box<dyn std::error::Error>> {
+//! let config = ScannerConfig::builder("example.com")
+//!     .method("GET")
+//!     .status_codes(Some("200".parse()?))
+//!     .ports("80,443".parse()?)
+//!     .build();
+//! let scanner = Scanner::new(config)?;
+//!
+//! let targets = Targets::Ranges {
+//!     ranges: vec!["203.0.113.0/24".parse()?],
+//!     skip: 0,
+//! };
+//! let mut events = Box::pin(scanner.scan(targets, CancellationToken::new()));
+//! while let Some(event) = events.next().await {
+//!     match event {
+//!         ScanEvent::Hit(hit) => println!("found {}", hit.endpoint()),
+//!         ScanEvent::Finished(summary) => println!("{} hit(s)", summary.hits.len()),
+//!         _ => {}
+//!     }
+//! }
+//! # Ok(())
+//! # }
+//! ```
+
+mod adaptive;
+pub mod baseline;
+pub mod checkpoint;
+mod conn;
+pub mod endpoints;
+mod event;
+mod http;
+pub mod matcher;
+pub mod output;
+mod ports;
+pub mod ranges;
+mod rate;
+mod scanner;
+mod simhash;
+mod stats;
+mod tls;
+
+pub use conn::Scheme;
+pub use event::{Phase, ScanEvent, ScanSummary, Targets};
+pub use http::{HttpResponse, StatusSet};
+pub use matcher::{MatchInput, Matcher};
+pub use ports::PortList;
+pub use scanner::{ScanHit, Scanner, ScannerConfig, ScannerConfigBuilder};
+pub use stats::ScanStats;
+pub use tls::CertVerification;
+pub use tokio_util::sync::CancellationToken;
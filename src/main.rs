@@ -1,19 +1,29 @@
-use clap::Parser;
+use clap::{CommandFactory, FromArgMatches, Parser};
 use colored::Colorize;
-use futures::stream::{self, StreamExt};
+use futures::StreamExt;
 use indicatif::{ProgressBar, ProgressStyle};
-use ipnetwork::Ipv4Network;
-use regex::Regex;
+use ipnetwork::IpNetwork;
+use octointel::baseline::{Baseline, BaselineOptions};
+use octointel::checkpoint::Checkpoint;
+use octointel::endpoints;
+use octointel::matcher::{self, SimhashMatcher};
+use octointel::output::{OutputFormat, ResultWriter};
+use octointel::ranges::{parse_ip_range, range_size, RangeTracker};
+use octointel::{
+    CancellationToken, CertVerification, Matcher, Phase, PortList, ScanEvent, ScanSummary, Scanner,
+    ScannerConfig, Scheme, StatusSet, Targets,
+};
+use sha2::{Digest, Sha256};
 use std::fs;
-use std::net::{IpAddr, Ipv4Addr, SocketAddr};
-use std::path::PathBuf;
-use std::sync::atomic::{AtomicBool, AtomicU64, Ordering};
-use std::sync::Arc;
-use std::time::{Duration, Instant};
+use std::net::{IpAddr, SocketAddr};
+use std::path::{Path, PathBuf};
+use std::sync::atomic::Ordering;
+use std::time::Duration;
 use sysinfo::System;
-use tokio::io::{AsyncReadExt, AsyncWriteExt};
-use tokio::net::TcpStream;
-use tokio::time::timeout;
+
+mod profile;
+
+use profile::Profile;
 
 #[derive(Parser, Debug)]
 #[command(
@@ -26,29 +36,69 @@ use tokio::time::timeout;
 )]
 struct Args {
     /// Target domain to scan for
-    #[arg(value_name = "DOMAIN")]
-    domain: String,
+    #[arg(value_name = "DOMAIN", required_unless_present = "config")]
+    domain: Option<String>,
+
+    /// Load scan settings from a TOML file; command-line flags override its values
+    #[arg(long, value_name = "FILE")]
+    config: Option<PathBuf>,
+
+    /// Print the effective configuration as TOML (loadable with --config) and exit
+    #[arg(long)]
+    print_config: bool,
 
-    /// IP ranges to scan (CIDR notation, e.g., 35.207.0.0/16)
+    /// IP ranges to scan (CIDR notation or single addresses, IPv4 or IPv6, e.g., 35.207.0.0/16,2001:db8::/120)
     #[arg(short, long, value_delimiter = ',')]
     ranges: Option<Vec<String>>,
 
     /// Path to file containing IP ranges (one CIDR per line)
-    #[arg(short = 'f', long, value_name = "FILE")]
+    #[arg(short = 'f', long, value_name = "FILE", conflicts_with = "ranges")]
     ip_file: Option<PathBuf>,
 
     /// HTTP method to use: HEAD, GET, or POST
     #[arg(short = 'm', long, default_value = "HEAD", value_parser = ["HEAD", "GET", "POST"])]
     method: String,
 
-    /// HTTP status code to match (default: 202)
-    #[arg(long, default_value = "202")]
-    status_code: u16,
+    /// HTTP status code(s) to match: codes, ranges and classes, e.g. 200,301-302,2xx (default: 202, or any status with --match, --baseline or --similar-to)
+    #[arg(long, value_name = "CODES")]
+    status_code: Option<StatusSet>,
 
     /// Search for specific content in HTML response (regex supported)
     #[arg(short = 'c', long)]
     content_match: Option<String>,
 
+    /// Match a response header value by regex (format: "Header: regex", can be specified multiple times)
+    #[arg(long = "header-match", value_name = "HEADER: REGEX")]
+    header_match: Option<Vec<String>>,
+
+    /// Match expression combining rules with and/or/not, e.g. "status=200 and title~Example and not header:Server~cloudflare"
+    #[arg(long = "match", value_name = "EXPR")]
+    match_expr: Option<String>,
+
+    /// Fetch the site through its public (CDN) address first and derive match rules from it: status, site headers, title and body simhash
+    #[arg(long, conflicts_with_all = ["cert_match", "cert_sha256", "spki_sha256"])]
+    baseline: bool,
+
+    /// Fetch the baseline from this address (IP or IP:PORT) instead of the domain's DNS address; implies --baseline
+    #[arg(long, value_name = "ADDR", conflicts_with_all = ["cert_match", "cert_sha256", "spki_sha256"])]
+    baseline_addr: Option<String>,
+
+    /// Match bodies similar to a reference page: a file, or an http:// or https:// URL (fetched twice so nonces are ignored)
+    #[arg(long, value_name = "FILE|URL", conflicts_with_all = ["cert_match", "cert_sha256", "spki_sha256"])]
+    similar_to: Option<String>,
+
+    /// Minimum body similarity, from 0 to 1, for --similar-to and the --baseline body rule [default: 0.8]
+    #[arg(long, value_name = "RATIO", value_parser = matcher::parse_similarity)]
+    similarity: Option<f64>,
+
+    /// Maximum response body size to read for content matching, in bytes
+    #[arg(long, value_name = "BYTES", default_value = "262144")]
+    max_body: usize,
+
+    /// Request path to probe (can be specified multiple times; each path is tried until one matches)
+    #[arg(long = "path", value_name = "PATH", default_value = "/")]
+    paths: Vec<String>,
+
     /// POST request body (when using POST method)
     #[arg(long)]
     post_body: Option<String>,
@@ -57,386 +107,223 @@ struct Args {
     #[arg(long = "header", value_name = "HEADER")]
     headers: Option<Vec<String>>,
 
-    /// Connection timeout in milliseconds (auto-detected if not specified)
+    /// Timeout in milliseconds, default for --connect-timeout and --read-timeout (auto-detected if not specified)
     #[arg(short, long)]
     timeout: Option<u64>,
 
+    /// TCP connect timeout in milliseconds (defaults to --timeout)
+    #[arg(long, value_name = "MS")]
+    connect_timeout: Option<u64>,
+
+    /// TLS handshake and HTTP response timeout in milliseconds (defaults to --timeout)
+    #[arg(long, value_name = "MS")]
+    read_timeout: Option<u64>,
+
+    /// Total time allowed per host across all paths and retries, in milliseconds
+    #[arg(long, value_name = "MS")]
+    host_deadline: Option<u64>,
+
+    /// Retries per probe after a transient error (timeout, connection reset)
+    #[arg(long, value_name = "N", default_value = "0")]
+    retries: u32,
+
     /// Maximum concurrent connections (auto-detected if not specified)
-    #[arg(short, long)]
+    #[arg(short, long, value_parser = clap::builder::RangedU64ValueParser::<usize>::new().range(1..))]
     workers: Option<usize>,
 
-    /// Scan a single IP address
-    #[arg(long)]
-    single_ip: Option<String>,
-
-    /// Stop immediately after finding first match
-    #[arg(long, default_value = "true")]
-    stop_on_find: bool,
+    /// Tokio worker threads (auto-detected if not specified)
+    #[arg(long, value_name = "N", value_parser = clap::value_parser!(u32).range(1..))]
+    threads: Option<u32>,
 
-    /// HTTP port to scan (default: 80)
-    #[arg(short = 'p', long, default_value = "80")]
-    port: u16,
+    /// Run the scanner on a single-threaded runtime (for low-resource containers)
+    #[arg(long, conflicts_with = "threads")]
+    current_thread: bool,
 
-    /// Use HTTPS instead of HTTP
+    /// Always run exactly --workers connections instead of adapting concurrency to observed timeouts and errors
     #[arg(long)]
-    https: bool,
+    no_adaptive: bool,
 
-    /// Verbose output for debugging
-    #[arg(short, long)]
-    verbose: bool,
-}
+    /// Maximum new connections per second across the whole scan
+    #[arg(long, value_name = "N", value_parser = clap::value_parser!(u32).range(1..))]
+    rate: Option<u32>,
 
-/// Configuration for creating a new Scanner instance
-pub struct ScannerConfig {
-    pub domain: String,
-    pub timeout: Duration,
-    pub workers: usize,
-    pub method: String,
-    pub status_code: u16,
-    pub content_match: Option<String>,
-    pub headers: Option<Vec<String>>,
-    pub post_body: Option<String>,
-    pub port: u16,
-    pub verbose: bool,
-}
+    /// Maximum new connections per second to any single /24 (IPv6: /64)
+    #[arg(long, value_name = "N", value_parser = clap::value_parser!(u32).range(1..))]
+    subnet_rate: Option<u32>,
 
-/// Scanner configuration and state management
-///
-/// Holds all configuration needed for scanning IP ranges, including:
-/// - Target domain and connection parameters
-/// - HTTP request configuration (method, headers, body)
-/// - Content matching rules (regex patterns)
-/// - Concurrency and performance settings
-struct Scanner {
-    domain: Arc<String>,
-    timeout: Duration,
-    workers: usize,
-    stop_flag: Arc<AtomicBool>,
-    found_count: Arc<AtomicU64>,
-    request_bytes: Arc<Vec<u8>>,
-    method: Arc<String>,
-    status_code: u16,
-    content_regex: Arc<Option<Regex>>,
-    port: u16,
-    verbose: bool,
-}
+    /// Scan a single IP address (IPv4 or IPv6)
+    #[arg(long, conflicts_with_all = ["ranges", "ip_file"])]
+    single_ip: Option<String>,
 
-impl Scanner {
-    /// Create a new Scanner instance with all configuration
-    ///
-    /// # Arguments
-    /// * `config` - Scanner configuration struct
-    ///
-    /// # Returns
-    /// * `Ok(Scanner)` - Configured scanner ready to use
-    /// * `Err` - If configuration is invalid (bad regex, invalid method, etc.)
-    fn new(config: ScannerConfig) -> Result<Self, Box<dyn std::error::Error>> {
-        // Build HTTP request with specified method
-        let mut request = match config.method.as_str() {
-            "HEAD" => format!("HEAD / HTTP/1.1\r\nHost: {}\r\n", config.domain),
-            "GET" => format!("GET / HTTP/1.1\r\nHost: {}\r\n", config.domain),
-            "POST" => {
-                let body = config.post_body.as_deref().unwrap_or("");
-                format!(
-                    "POST / HTTP/1.1\r\nHost: {}\r\nContent-Length: {}\r\n",
-                    config.domain,
-                    body.len()
-                )
-            }
-            _ => return Err(format!("Unsupported HTTP method: {}", config.method).into()),
-        };
+    /// Stop immediately after finding first match (default behavior)
+    #[arg(long, conflicts_with_all = ["all", "max_finds"])]
+    stop_on_find: bool,
 
-        // Add custom headers if provided
-        if let Some(ref custom_headers) = config.headers {
-            for header in custom_headers {
-                if !header.contains(':') {
-                    return Err(format!(
-                        "Invalid header format: '{}'. Expected 'Header: Value'",
-                        header
-                    )
-                    .into());
-                }
-                request.push_str(header);
-                request.push_str("\r\n");
-            }
-        }
+    /// Keep scanning after the first match and report every backend
+    #[arg(long = "all", visible_alias = "no-stop-on-find")]
+    all: bool,
+
+    /// Stop after N matches across all ranges
+    #[arg(long, value_name = "N", value_parser = clap::value_parser!(u64).range(1..))]
+    max_finds: Option<u64>,
+
+    /// Port(s) to scan on every address: ports and ranges, e.g. 80,443,8080-8090
+    #[arg(
+        short = 'p',
+        long = "port",
+        visible_alias = "ports",
+        value_name = "PORTS",
+        default_value = "80"
+    )]
+    ports: PortList,
+
+    /// Protocol per port: auto (80 plaintext, 443 TLS, others detected), http or https
+    #[arg(long, value_enum, default_value = "auto", conflicts_with = "https")]
+    scheme: Scheme,
+
+    /// Use HTTPS on every port, same as --scheme https (SNI is set to the target domain)
+    #[arg(long)]
+    https: bool,
 
-        // Add standard headers
-        request.push_str("Connection: close\r\n");
-        request.push_str("User-Agent: octointel/2.0\r\n");
+    /// Skip TLS certificate verification when using HTTPS
+    #[arg(short = 'k', long, conflicts_with = "ca_file")]
+    insecure: bool,
 
-        // Complete headers and add body for POST
-        request.push_str("\r\n");
-        if config.method == "POST" {
-            if let Some(body) = config.post_body {
-                request.push_str(&body);
-            }
-        }
+    /// Verify TLS certificates against CA certificates in this PEM file instead of the system roots
+    #[arg(long, value_name = "FILE")]
+    ca_file: Option<PathBuf>,
 
-        // Compile regex if content matching is enabled
-        let content_regex = if let Some(pattern) = config.content_match {
-            match Regex::new(&pattern) {
-                Ok(re) => Some(re),
-                Err(e) => return Err(format!("Invalid regex pattern: {}", e).into()),
-            }
-        } else {
-            None
-        };
+    /// Probe hosts whose certificate fails system root verification again without it, flagging their hits as untrusted
+    #[arg(long, conflicts_with_all = ["insecure", "ca_file"])]
+    retry_untrusted: bool,
 
-        Ok(Self {
-            domain: Arc::new(config.domain),
-            timeout: config.timeout,
-            workers: config.workers,
-            stop_flag: Arc::new(AtomicBool::new(false)),
-            found_count: Arc::new(AtomicU64::new(0)),
-            request_bytes: Arc::new(request.into_bytes()),
-            method: Arc::new(config.method),
-            status_code: config.status_code,
-            content_regex: Arc::new(content_regex),
-            port: config.port,
-            verbose: config.verbose,
-        })
-    }
+    /// Match by TLS certificate instead of HTTP: hit if the SAN list (or CN) covers the domain
+    #[arg(long)]
+    cert_match: bool,
 
-    /// Scan a single IP address for the target domain
-    ///
-    /// # Arguments
-    /// * `ip` - IPv4 address to scan
-    ///
-    /// # Returns
-    /// * `Some((ip, info))` - If match found, returns IP and match details
-    /// * `None` - If no match or connection failed
-    ///
-    /// # Behavior
-    /// - Connects to ip:port via TCP
-    /// - Sends configured HTTP request (HEAD/GET/POST)
-    /// - Checks for matching status code
-    /// - Optionally validates content with regex
-    /// - Returns immediately if stop_flag is set
-    async fn scan_ip(&self, ip: Ipv4Addr) -> Option<(String, String)> {
-        // Check stop flag early (avoid unnecessary work)
-        if self.stop_flag.load(Ordering::Relaxed) {
-            return None;
-        }
+    /// Match by TLS certificate SHA-256 fingerprint (hex, colons allowed)
+    #[arg(long, value_name = "SHA256")]
+    cert_sha256: Option<String>,
 
-        let socket_addr = SocketAddr::new(IpAddr::V4(ip), self.port);
+    /// Match by SHA-256 hash of the certificate's SubjectPublicKeyInfo (hex, colons allowed)
+    #[arg(long, value_name = "SHA256")]
+    spki_sha256: Option<String>,
 
-        if self.verbose {
-            println!("{} Scanning {}:{}", "→".bright_cyan(), ip, self.port);
-        }
+    /// Write hits to this file as they are found
+    #[arg(short = 'o', long, value_name = "FILE")]
+    output: Option<PathBuf>,
 
-        // Attempt connection with timeout
-        match timeout(self.timeout, TcpStream::connect(socket_addr)).await {
-            Ok(Ok(mut stream)) => {
-                // Disable Nagle's algorithm for faster small packets
-                let _ = stream.set_nodelay(true);
+    /// Format of the --output file
+    #[arg(long, value_enum, default_value = "jsonl", requires = "output")]
+    format: OutputFormat,
 
-                // Send HTTP request
-                if let Err(e) = stream.write_all(&self.request_bytes).await {
-                    if self.verbose {
-                        eprintln!("{} Failed to write to {}: {}", "✗".red(), ip, e);
-                    }
-                    return None;
-                }
+    /// Where to write the resume checkpoint if the scan is interrupted (Ctrl-C)
+    #[arg(long, value_name = "FILE", default_value = "octointel.checkpoint.json")]
+    checkpoint: PathBuf,
 
-                // Read response - use larger buffer for content matching
-                let buffer_size = if self.content_regex.is_some() || self.method.as_str() == "GET" {
-                    8192 // 8KB for full response content
-                } else {
-                    512 // Small buffer for status line only
-                };
-
-                let mut buffer = vec![0u8; buffer_size];
-
-                match timeout(self.timeout, stream.read(&mut buffer)).await {
-                    Ok(Ok(bytes_read)) => {
-                        if bytes_read == 0 {
-                            return None;
-                        }
-
-                        // Convert to string for parsing
-                        let response = String::from_utf8_lossy(&buffer[..bytes_read]);
-
-                        // Parse HTTP status code
-                        let status_match = format!(" {} ", self.status_code);
-                        let has_status = response.contains(&status_match);
-
-                        // Check content if regex is provided
-                        let content_matched = if let Some(ref regex) = *self.content_regex {
-                            regex.is_match(&response)
-                        } else {
-                            true // No content filter, so consider it matched
-                        };
-
-                        if has_status && content_matched {
-                            let info = if self.content_regex.is_some() {
-                                format!("Status: {}, Content matched", self.status_code)
-                            } else {
-                                format!("Status: {}", self.status_code)
-                            };
-                            return Some((ip.to_string(), info));
-                        }
-
-                        if self.verbose && has_status {
-                            println!(
-                                "{} {} returned {} but content didn't match",
-                                "ℹ".bright_blue(),
-                                ip,
-                                self.status_code
-                            );
-                        }
-                    }
-                    Ok(Err(e)) => {
-                        if self.verbose {
-                            eprintln!("{} Failed to read from {}: {}", "✗".red(), ip, e);
-                        }
-                    }
-                    Err(_) => {
-                        if self.verbose {
-                            eprintln!("{} Read timeout for {}", "✗".red(), ip);
-                        }
-                    }
-                }
-            }
-            Ok(Err(e)) => {
-                if self.verbose {
-                    eprintln!("{} Connection failed for {}: {}", "✗".red(), ip, e);
-                }
-            }
-            Err(_) => {
-                if self.verbose {
-                    eprintln!("{} Connection timeout for {}", "✗".red(), ip);
-                }
-            }
-        }
+    /// Resume an interrupted scan from a checkpoint file (ranges come from the checkpoint)
+    #[arg(long, value_name = "FILE", conflicts_with_all = ["ranges", "ip_file", "single_ip"])]
+    resume: Option<PathBuf>,
 
-        None
-    }
+    /// Two-phase scan: first find hosts with the port open (TCP connect only), then probe only those
+    #[arg(long, conflicts_with_all = ["resume", "single_ip"])]
+    two_phase: bool,
 
-    /// Scan an entire IP range (CIDR notation)
-    ///
-    /// # Arguments
-    /// * `range` - CIDR notation (e.g., "35.207.0.0/16")
-    /// * `stop_on_find` - Whether to stop after first match
-    ///
-    /// # Returns
-    /// * Vector of (ip, info) tuples for all matches found
-    ///
-    /// # Behavior
-    /// - Parses CIDR range into individual IPs
-    /// - Creates concurrent scan tasks (up to `workers` parallel)
-    /// - Shows progress bar with real-time stats
-    /// - Stops early if `stop_on_find` is true and match is found
-    async fn scan_range(&self, range: &str, stop_on_find: bool) -> Vec<(String, String)> {
-        let network: Ipv4Network = match range.parse() {
-            Ok(net) => net,
-            Err(e) => {
-                eprintln!("{} Failed to parse range {}: {}", "✗".red(), range, e);
-                return Vec::new();
-            }
-        };
+    /// Save the open endpoints found by --two-phase discovery to this file (one ip:port per line)
+    #[arg(long, value_name = "FILE", requires = "two_phase")]
+    save_open: Option<PathBuf>,
 
-        let total_ips = network.size() as u64;
-        let ips: Vec<Ipv4Addr> = network.iter().collect();
+    /// Skip discovery and probe only the endpoints listed in this file (written by --save-open)
+    #[arg(long, value_name = "FILE", conflicts_with_all = ["ranges", "ip_file", "single_ip", "resume", "two_phase"])]
+    open_from: Option<PathBuf>,
 
-        println!(
-            "\n{}\n{} Scanning {} IPs in range {}\n{}",
-            "=".repeat(60).bright_cyan(),
-            "➤".bright_green(),
-            total_ips,
-            range.bright_yellow(),
-            "=".repeat(60).bright_cyan()
-        );
+    /// Verbose output for debugging
+    #[arg(short, long)]
+    verbose: bool,
+}
 
-        let progress = ProgressBar::new(total_ips);
-        progress.set_style(
-            ProgressStyle::default_bar()
-                .template("[{elapsed_precise}] [{bar:40.cyan/blue}] {pos}/{len} ({percent}%) | {per_sec} IPs/sec | ETA: {eta}")
-                .unwrap()
-                .progress_chars("█▓▒░"),
-        );
+/// File descriptors kept free for stdio, result/checkpoint files, TLS roots, etc.
+const FD_RESERVE: u64 = 64;
 
-        let found_ips = Arc::new(tokio::sync::Mutex::new(Vec::new()));
-        let found_ips_clone = found_ips.clone();
-        let stop_flag = self.stop_flag.clone();
-        let found_count = self.found_count.clone();
-        let request_bytes = self.request_bytes.clone();
-        let domain = self.domain.clone();
-        let method = self.method.clone();
-        let status_code = self.status_code;
-        let content_regex = self.content_regex.clone();
-        let port = self.port;
-        let verbose = self.verbose;
-
-        // Create stream of scan tasks
-        let mut stream = stream::iter(ips)
-            .map(|ip| {
-                let scanner = Scanner {
-                    domain: domain.clone(),
-                    timeout: self.timeout,
-                    workers: self.workers,
-                    stop_flag: stop_flag.clone(),
-                    found_count: found_count.clone(),
-                    request_bytes: request_bytes.clone(),
-                    method: method.clone(),
-                    status_code,
-                    content_regex: content_regex.clone(),
-                    port,
-                    verbose,
-                };
-                let progress = progress.clone();
-                let found_ips = found_ips_clone.clone();
-                let stop_flag_inner = stop_flag.clone();
-                let found_count_inner = found_count.clone();
-
-                async move {
-                    let result = scanner.scan_ip(ip).await;
-                    progress.inc(1);
-
-                    if let Some((found_ip, info)) = result {
-                        println!(
-                            "\n{} {} - {}",
-                            "✓ FOUND:".bright_green().bold(),
-                            found_ip.bright_yellow().bold(),
-                            info.bright_white()
-                        );
-
-                        found_ips
-                            .lock()
-                            .await
-                            .push((found_ip.clone(), info.clone()));
-                        found_count_inner.fetch_add(1, Ordering::Relaxed);
-
-                        if stop_on_find {
-                            stop_flag_inner.store(true, Ordering::Relaxed);
-                            println!(
-                                "\n{} Backend IP found! Stopping scan immediately...\n",
-                                "⚠".bright_yellow()
-                            );
-                        }
-
-                        Some((found_ip, info))
-                    } else {
-                        None
-                    }
-                }
-            })
-            .buffer_unordered(self.workers);
+/// Raise the open-file limit as far as needed and clamp workers to it
+///
+/// # Arguments
+/// * `workers` - Requested (or auto-detected) number of concurrent connections
+///
+/// # Returns
+/// * Number of workers the open-file limit can actually support
+///
+/// # Behavior
+/// - Reads the soft/hard RLIMIT_NOFILE and raises the soft limit towards
+///   `workers` plus a small reserve (never beyond the hard limit)
+/// - Every in-flight connection holds one descriptor, so workers above the
+///   resulting limit would only fail with "Too many open files"
+/// - Warns when the limit is what caps the worker count
+#[cfg(unix)]
+fn clamp_workers_to_fd_limit(workers: usize) -> usize {
+    use rlimit::Resource;
+
+    let (soft, hard) = match Resource::NOFILE.get() {
+        Ok(limits) => limits,
+        Err(e) => {
+            eprintln!(
+                "{} Could not read open file limit ({}), using {} workers as is",
+                "⚠".bright_yellow(),
+                e,
+                workers
+            );
+            return workers;
+        }
+    };
 
-        // Process results
-        while (stream.next().await).is_some() {
-            if self.stop_flag.load(Ordering::Relaxed) {
-                break;
+    let wanted = (workers as u64).saturating_add(FD_RESERVE).min(hard);
+    let limit = if wanted > soft {
+        match rlimit::increase_nofile_limit(wanted) {
+            Ok(raised) => {
+                println!(
+                    "{} Raised open file limit from {} to {} (hard limit {})",
+                    "ℹ".bright_blue(),
+                    soft,
+                    raised,
+                    hard
+                );
+                raised
+            }
+            Err(e) => {
+                eprintln!(
+                    "{} Failed to raise open file limit from {}: {}",
+                    "⚠".bright_yellow(),
+                    soft,
+                    e
+                );
+                soft
             }
         }
+    } else {
+        soft
+    };
 
-        progress.finish_and_clear();
-
-        let found = found_ips.lock().await;
-        found.clone()
+    let available = limit.saturating_sub(FD_RESERVE).max(1);
+    if (workers as u64) > available {
+        eprintln!(
+            "{} Open file limit {} caps workers at {} (requested {}); raise it with `ulimit -n` to scan faster",
+            "⚠".bright_yellow(),
+            limit,
+            available,
+            workers
+        );
+        available as usize
+    } else {
+        workers
     }
 }
 
+/// Open-file limits are a Unix concept; other platforms keep the requested workers
+#[cfg(not(unix))]
+fn clamp_workers_to_fd_limit(workers: usize) -> usize {
+    workers
+}
+
 /// Detect optimal system settings for maximum performance
 ///
 /// # Returns
@@ -519,7 +406,8 @@ fn detect_optimal_settings() -> (usize, u64, usize) {
 /// * `Err` - If file cannot be read or contains no valid ranges
 ///
 /// # Format
-/// - One CIDR range per line (e.g., "35.207.0.0/16")
+/// - One CIDR range or address per line (e.g., "35.207.0.0/16", "2001:db8::10")
+/// - IPv6 prefixes must be /112 or smaller
 /// - Lines starting with '#' or '//' are treated as comments
 /// - Empty lines are ignored
 fn load_ip_ranges_from_file(
@@ -545,7 +433,7 @@ fn load_ip_ranges_from_file(
         }
 
         // Validate CIDR notation before adding to list
-        match trimmed.parse::<Ipv4Network>() {
+        match parse_ip_range(trimmed) {
             Ok(_) => ranges.push(trimmed.to_string()),
             Err(e) => {
                 eprintln!(
@@ -572,9 +460,396 @@ fn load_ip_ranges_from_file(
     Ok(ranges)
 }
 
-#[tokio::main(flavor = "multi_thread")]
-async fn main() {
-    let args = Args::parse();
+/// Print how far each range got, listing only ranges with hits or targets left
+///
+/// # Arguments
+/// * `ranges` - Counters of the finished scan (or of discovery, for two-phase scans)
+/// * `discovery` - Whether the counters describe open endpoints rather than hits
+fn print_range_summary(ranges: &RangeTracker, discovery: bool) {
+    println!(
+        "{} Ranges: {}/{} fully scanned",
+        "ℹ".bright_blue(),
+        ranges.finished(),
+        ranges.ranges().len()
+    );
+    for range in ranges.ranges() {
+        let found = range.found.load(Ordering::Relaxed);
+        if found == 0 && range.is_finished() {
+            continue;
+        }
+        println!(
+            "  {} {}: {}/{} target(s), {} {}",
+            "→".bright_cyan(),
+            range.network,
+            range.done.load(Ordering::Relaxed),
+            range.total,
+            found,
+            if discovery { "open" } else { "hit(s)" }
+        );
+    }
+}
+
+/// Terminal rendering of a running scan, plus the results file
+struct ScanView<'a> {
+    /// Ranges of a range scan (empty when probing endpoints), for phase headers
+    ranges: &'a [IpNetwork],
+    ports: &'a PortList,
+    two_phase: bool,
+    /// Single-IP mode prints its own header
+    single_ip: bool,
+    /// Where to save the endpoints found by discovery
+    save_open: Option<&'a Path>,
+    /// Results file, if one was requested
+    writer: Option<ResultWriter>,
+}
+
+impl ScanView<'_> {
+    /// Print the banner for a phase that is starting
+    fn print_header(&self, phase: Phase, total: u64, skip: u64) {
+        let ips: u64 = self.ranges.iter().map(range_size).sum();
+        let title = match phase {
+            _ if self.single_ip => return,
+            Phase::Discovery => format!(
+                "Discovering open port(s) {} on {} IPs in {} range(s)",
+                self.ports,
+                ips,
+                self.ranges.len()
+            ),
+            Phase::Probe if !self.ranges.is_empty() && !self.two_phase => format!(
+                "Scanning {} IPs{} in {} range(s){}",
+                ips,
+                match self.ports.count() {
+                    1 => String::new(),
+                    n => format!(" × {} ports", n),
+                },
+                self.ranges.len(),
+                if skip > 0 {
+                    format!(" (resuming after {})", skip)
+                } else {
+                    String::new()
+                }
+            ),
+            Phase::Probe => format!("Probing {} open endpoint(s)", total),
+        };
+
+        println!(
+            "\n{}\n{} {}\n{}",
+            "=".repeat(60).bright_cyan(),
+            "➤".bright_green(),
+            title,
+            "=".repeat(60).bright_cyan()
+        );
+    }
+
+    /// Append a hit to the results file, if one was requested
+    fn record_hit(&mut self, hit: &octointel::ScanHit) {
+        if let Some(ref mut writer) = self.writer {
+            if let Err(e) = writer.write_hit(hit) {
+                eprintln!("{} Failed to write result: {}", "✗".red(), e);
+            }
+        }
+    }
+
+    /// Finish the results file (closes the JSON array, flushes buffers)
+    fn finish_output(&mut self) {
+        if let Some(ref mut writer) = self.writer {
+            if let Err(e) = writer.finish() {
+                eprintln!("{} Failed to finish output file: {}", "✗".red(), e);
+            }
+        }
+    }
+}
+
+/// Parse a `--baseline-addr` value: an IP address with an optional port
+///
+/// # Returns
+/// * `Ok((ip, port))` - `port` is `None` for a bare address
+/// * `Err` - If the value is neither `IP` nor `IP:PORT` (`[IPv6]:PORT`)
+fn parse_baseline_addr(value: &str) -> Result<(IpAddr, Option<u16>), String> {
+    if let Ok(ip) = value.parse::<IpAddr>() {
+        return Ok((ip, None));
+    }
+    value
+        .parse::<SocketAddr>()
+        .map(|addr| (addr.ip(), Some(addr.port())))
+        .map_err(|_| {
+            format!(
+                "Invalid baseline address '{}': expected IP or IP:PORT",
+                value
+            )
+        })
+}
+
+/// Fingerprint stored in checkpoints to reject resuming with a different configuration
+///
+/// # Arguments
+/// * `config` - Scanner configuration; its custom matchers are the rules
+///   derived from live pages and are left out
+/// * `args` - Command line, for the flags those rules came from
+/// * `min_similarity` - Similarity threshold of the derived body rules
+///
+/// Baseline and reference-page rules are fetched again on every run and
+/// drift with the site (simhash, headers), so the flags that produced them
+/// are fingerprinted instead; a resumed scan then uses freshly derived rules.
+fn checkpoint_fingerprint(config: &ScannerConfig, args: &Args, min_similarity: f64) -> String {
+    let without_derived = ScannerConfig {
+        matchers: Vec::new(),
+        ..config.clone()
+    };
+    let canonical = format!(
+        "{}|baseline={}|baseline_addr={:?}|similar_to={:?}|similarity={}",
+        without_derived.fingerprint(),
+        args.baseline,
+        args.baseline_addr,
+        args.similar_to,
+        min_similarity,
+    );
+    Sha256::digest(canonical.as_bytes())
+        .iter()
+        .map(|b| format!("{:02x}", b))
+        .collect()
+}
+
+/// Print what the baseline recorded and the rules derived from it
+fn print_baseline(domain: &str, path: &str, baseline: &Baseline, rules: &str, with_body: bool) {
+    println!(
+        "{} Baseline: {}://{}{} via {}",
+        "ℹ".bright_blue(),
+        baseline.scheme,
+        domain,
+        path,
+        baseline.endpoint
+    );
+    println!("  {} Status: {}", "→".bright_cyan(), baseline.status);
+    if let Some(ref title) = baseline.title {
+        println!("  {} Title: {}", "→".bright_cyan(), title);
+    }
+    for (name, value) in &baseline.headers {
+        println!("  {} Site header: {}: {}", "→".bright_cyan(), name, value);
+    }
+    if let Some(ref server) = baseline.cdn_server {
+        println!(
+            "  {} CDN server: {} (excluded from hits)",
+            "→".bright_cyan(),
+            server
+        );
+    }
+    match baseline.simhash {
+        Some(hash) => println!(
+            "  {} Body simhash: {:016x} ({} stable features)",
+            "→".bright_cyan(),
+            hash,
+            baseline.features
+        ),
+        None => println!(
+            "  {} Body simhash: none (only {} stable features)",
+            "→".bright_cyan(),
+            baseline.features
+        ),
+    }
+    if let Some(hash) = baseline.favicon_hash {
+        println!(
+            "  {} Favicon hash: {} (matches --path /favicon.ico probes; search http.favicon.hash:{})",
+            "→".bright_cyan(),
+            hash,
+            hash
+        );
+    }
+    if !with_body {
+        println!(
+            "{} HEAD responses have no body: title, body simhash and favicon hash are not used (use -m GET)",
+            "⚠".bright_yellow()
+        );
+    }
+    println!(
+        "  {} Derived rules: {}",
+        "→".bright_cyan(),
+        rules.bright_yellow()
+    );
+    println!();
+}
+
+/// Progress bar for one phase of the scan
+fn phase_progress_bar(total: u64, skip: u64) -> ProgressBar {
+    let progress = ProgressBar::new(total).with_position(skip);
+    progress.set_style(
+        ProgressStyle::default_bar()
+            .template("[{elapsed_precise}] [{bar:40.cyan/blue}] {pos}/{len} ({percent}%) | {per_sec} IPs/sec | ETA: {eta}{msg}")
+            .unwrap()
+            .progress_chars("█▓▒░"),
+    );
+    progress
+}
+
+/// Run a scan and render its events on the terminal
+///
+/// # Arguments
+/// * `scanner` - Scanner to run
+/// * `targets` - Ranges or endpoints to scan
+/// * `cancel` - Token cancelled by the Ctrl-C handler
+/// * `view` - Rendering context and results file
+///
+/// # Returns
+/// * Summary of the finished (or cancelled) scan
+async fn run_scan(
+    scanner: Scanner,
+    targets: Targets,
+    cancel: CancellationToken,
+    view: &mut ScanView<'_>,
+) -> ScanSummary {
+    let mut events = Box::pin(scanner.scan(targets, cancel));
+    let mut progress: Option<ProgressBar> = None;
+
+    while let Some(event) = events.next().await {
+        match event {
+            ScanEvent::PhaseStarted { phase, total, skip } => {
+                if let Some(progress) = progress.take() {
+                    progress.finish_and_clear();
+                }
+                view.print_header(phase, total, skip);
+                progress = Some(phase_progress_bar(total, skip));
+            }
+            ScanEvent::Progress {
+                done,
+                total,
+                status,
+                ..
+            } => {
+                if let Some(ref progress) = progress {
+                    progress.set_length(total);
+                    progress.set_position(done);
+                    if !status.is_empty() {
+                        progress.set_message(format!(" | {}", status));
+                    }
+                }
+            }
+            ScanEvent::RangeFinished {
+                phase,
+                network,
+                total,
+                found,
+            } => {
+                println!(
+                    "{} Finished range {}: {} target(s), {} {}",
+                    "✓".green(),
+                    network,
+                    total,
+                    found,
+                    match phase {
+                        Phase::Discovery => "open",
+                        Phase::Probe => "hit(s)",
+                    }
+                );
+            }
+            ScanEvent::Discovered(open) => {
+                println!(
+                    "\n{} Discovery found {} open endpoint(s)",
+                    "ℹ".bright_blue(),
+                    open.len()
+                );
+                if let Some(path) = view.save_open {
+                    match endpoints::save_endpoints(path, &open) {
+                        Ok(()) => println!(
+                            "{} Open endpoints written to {} - probe them again with: --open-from {}",
+                            "ℹ".bright_blue(),
+                            path.display(),
+                            path.display()
+                        ),
+                        Err(e) => eprintln!("{} Failed to write open endpoints: {}", "✗".red(), e),
+                    }
+                }
+            }
+            ScanEvent::Hit(hit) => {
+                println!(
+                    "\n{} {} - {}",
+                    "✓ FOUND:".bright_green().bold(),
+                    hit.endpoint().to_string().bright_yellow().bold(),
+                    hit.summary().bright_white()
+                );
+                view.record_hit(&hit);
+            }
+            ScanEvent::Error { target, message } => {
+                eprintln!("{} {}: {}", "✗".red(), target, message);
+            }
+            ScanEvent::Debug { target, message } => {
+                println!("{} {}: {}", "ℹ".bright_blue(), target, message);
+            }
+            ScanEvent::Finished(summary) => {
+                if let Some(progress) = progress.take() {
+                    progress.finish_and_clear();
+                }
+                return summary;
+            }
+            ScanEvent::Failed(message) => {
+                if let Some(progress) = progress.take() {
+                    progress.finish_and_clear();
+                }
+                eprintln!("{} Scan failed: {}", "✗".red(), message);
+                std::process::exit(1);
+            }
+        }
+    }
+
+    eprintln!("{} Scan ended without a summary", "✗".red());
+    std::process::exit(1);
+}
+
+/// Build the Tokio runtime the scan runs on
+///
+/// # Arguments
+/// * `current_thread` - Use a single-threaded runtime instead of a thread pool
+/// * `worker_threads` - Number of worker threads for the multi-threaded runtime
+fn build_runtime(
+    current_thread: bool,
+    worker_threads: usize,
+) -> std::io::Result<tokio::runtime::Runtime> {
+    let mut builder = if current_thread {
+        tokio::runtime::Builder::new_current_thread()
+    } else {
+        let mut builder = tokio::runtime::Builder::new_multi_thread();
+        builder.worker_threads(worker_threads);
+        builder
+    };
+    builder.enable_all().build()
+}
+
+fn main() {
+    let matches = Args::command().get_matches();
+    let mut args = Args::from_arg_matches(&matches).unwrap_or_else(|e| e.exit());
+
+    // Fill in everything the command line left out from the profile
+    if let Some(path) = args.config.clone() {
+        let applied = Profile::load(&path)
+            .map_err(|e| e.to_string())
+            .and_then(|profile| profile.apply(&mut args, &matches));
+        if let Err(e) = applied {
+            eprintln!(
+                "{} Invalid config file {}: {}",
+                "✗".red(),
+                path.display(),
+                e
+            );
+            std::process::exit(1);
+        }
+    }
+    if args.domain.is_none() {
+        eprintln!(
+            "{} No target domain: pass DOMAIN or set `domain` in the config file",
+            "✗".red()
+        );
+        std::process::exit(1);
+    }
+
+    if args.print_config {
+        match Profile::from_args(&args).to_toml() {
+            Ok(toml) => print!("{}", toml),
+            Err(e) => {
+                eprintln!("{} Failed to render configuration: {}", "✗".red(), e);
+                std::process::exit(1);
+            }
+        }
+        return;
+    }
 
     // Print banner
     println!(
@@ -590,25 +865,284 @@ async fn main() {
     }
 
     // Auto-detect optimal settings if not provided
-    let (optimal_workers, optimal_timeout, _worker_threads) = detect_optimal_settings();
+    let (optimal_workers, optimal_timeout, worker_threads) = detect_optimal_settings();
+
+    // Build the runtime from the detected or requested thread count
+    let worker_threads = args.threads.map_or(worker_threads, |n| n as usize);
+    let runtime = match build_runtime(args.current_thread, worker_threads) {
+        Ok(runtime) => runtime,
+        Err(e) => {
+            eprintln!("{} Failed to start Tokio runtime: {}", "✗".red(), e);
+            std::process::exit(1);
+        }
+    };
+    if args.current_thread {
+        println!("{} Using a single-threaded runtime", "ℹ".bright_blue());
+    } else if args.threads.is_some() {
+        println!(
+            "{} Using {} Tokio worker threads",
+            "ℹ".bright_blue(),
+            worker_threads
+        );
+    }
+
+    runtime.block_on(run(args, optimal_workers, optimal_timeout));
+}
+
+/// Run the scan described by the command line on the current runtime
+///
+/// # Arguments
+/// * `args` - Parsed command-line arguments
+/// * `optimal_workers` - Auto-detected worker count, used when `--workers` is not given
+/// * `optimal_timeout` - Auto-detected timeout in ms, used when `--timeout` is not given
+async fn run(args: Args, optimal_workers: usize, optimal_timeout: u64) {
+    let domain = args
+        .domain
+        .clone()
+        .expect("domain is checked before running");
 
     // Use provided values or auto-detected ones
-    let workers = args.workers.unwrap_or(optimal_workers);
+    let workers = clamp_workers_to_fd_limit(args.workers.unwrap_or(optimal_workers));
     let timeout = args.timeout.unwrap_or(optimal_timeout);
+    let connect_timeout = args.connect_timeout.unwrap_or(timeout);
+    let read_timeout = args.read_timeout.unwrap_or(timeout);
+
+    // Pick how HTTPS certificates are validated
+    let cert_verification = if args.insecure {
+        CertVerification::Skip
+    } else if let Some(ref ca_file) = args.ca_file {
+        CertVerification::CustomRoots(ca_file.clone())
+    } else {
+        CertVerification::SystemRoots
+    };
+
+    // Stop after the first match unless --all or --max-finds says otherwise
+    let max_finds = match (args.max_finds, args.all) {
+        (Some(limit), _) => Some(limit),
+        (None, true) => None,
+        (None, false) => Some(1),
+    };
+
+    // Endpoints saved by an earlier discovery run replace the IP ranges entirely
+    let open_endpoints = match args.open_from {
+        Some(ref path) => match endpoints::load_endpoints(path) {
+            Ok(endpoints) => Some(endpoints),
+            Err(e) => {
+                eprintln!(
+                    "{} Failed to load endpoints from {}: {}",
+                    "✗".red(),
+                    path.display(),
+                    e
+                );
+                std::process::exit(1);
+            }
+        },
+        None => None,
+    };
+
+    // --https is shorthand for --scheme https
+    let scheme = if args.https {
+        Scheme::Https
+    } else {
+        args.scheme
+    };
+
+    // Saved endpoints carry their own ports
+    let ports = match open_endpoints {
+        Some(ref endpoints) => endpoints.iter().map(SocketAddr::port).collect(),
+        None => args.ports.clone(),
+    };
+
+    let min_similarity = args.similarity.unwrap_or(matcher::SIMHASH_MIN_SIMILARITY);
+
+    // Fetch the site through its public address and derive rules from it
+    let baseline_rules = if args.baseline || args.baseline_addr.is_some() {
+        if args.paths.len() > 1 {
+            eprintln!(
+                "{} --baseline derives rules from a single page; pass only one --path",
+                "✗".red()
+            );
+            std::process::exit(1);
+        }
+        let (address, port) = match args.baseline_addr.as_deref().map(parse_baseline_addr) {
+            Some(Ok((ip, port))) => (Some(ip), port),
+            Some(Err(e)) => {
+                eprintln!("{} {}", "✗".red(), e);
+                std::process::exit(1);
+            }
+            None => (None, None),
+        };
+        let options = BaselineOptions {
+            address,
+            port,
+            scheme,
+            path: args.paths[0].clone(),
+            headers: args.headers.clone().unwrap_or_default(),
+            verification: cert_verification.clone(),
+            max_body: args.max_body,
+            ..BaselineOptions::default()
+        };
+        let baseline = match Baseline::fetch(&domain, &options).await {
+            Ok(baseline) => baseline,
+            Err(e) => {
+                eprintln!("{} Failed to fetch baseline: {}", "✗".red(), e);
+                std::process::exit(1);
+            }
+        };
+        let with_body = args.method != "HEAD";
+        let rules = baseline.matcher(with_body, min_similarity);
+        print_baseline(
+            &domain,
+            &options.path,
+            &baseline,
+            &rules.describe(),
+            with_body,
+        );
+        Some(rules)
+    } else {
+        None
+    };
+
+    // Compare bodies with a reference page, read from a file or fetched from a URL
+    if args.similar_to.is_some() && args.method == "HEAD" {
+        eprintln!(
+            "{} --similar-to compares response bodies, which HEAD responses don't have; use -m GET",
+            "✗".red()
+        );
+        std::process::exit(1);
+    }
+    let similar_rule = match args.similar_to {
+        Some(ref source) if source.starts_with("http://") || source.starts_with("https://") => {
+            let options = BaselineOptions {
+                headers: args.headers.clone().unwrap_or_default(),
+                verification: cert_verification.clone(),
+                max_body: args.max_body,
+                ..BaselineOptions::default()
+            };
+            match Baseline::fetch_url(source, options).await {
+                Ok(Baseline {
+                    simhash: Some(hash),
+                    ..
+                }) => Some(SimhashMatcher::new(hash, min_similarity)),
+                Ok(_) => {
+                    eprintln!(
+                        "{} Reference page {} has too little stable text to compare",
+                        "✗".red(),
+                        source
+                    );
+                    std::process::exit(1);
+                }
+                Err(e) => {
+                    eprintln!(
+                        "{} Failed to fetch reference page {}: {}",
+                        "✗".red(),
+                        source,
+                        e
+                    );
+                    std::process::exit(1);
+                }
+            }
+        }
+        Some(ref source) => match fs::read(source) {
+            Ok(body) => Some(SimhashMatcher::similar_to(
+                &String::from_utf8_lossy(&body),
+                min_similarity,
+            )),
+            Err(e) => {
+                eprintln!(
+                    "{} Failed to read reference page {}: {}",
+                    "✗".red(),
+                    source,
+                    e
+                );
+                std::process::exit(1);
+            }
+        },
+        None => None,
+    };
+    let similar_desc = similar_rule.as_ref().map(|rule| rule.describe());
 
     // Create scanner with all the new options
-    let scanner = match Scanner::new(ScannerConfig {
-        domain: args.domain.clone(),
-        timeout: Duration::from_millis(timeout),
-        workers,
-        method: args.method.clone(),
-        status_code: args.status_code,
-        content_match: args.content_match.clone(),
-        headers: args.headers.clone(),
-        post_body: args.post_body.clone(),
-        port: args.port,
-        verbose: args.verbose,
-    }) {
+    let mut config = ScannerConfig::builder(domain.clone())
+        .connect_timeout(Duration::from_millis(connect_timeout))
+        .read_timeout(Duration::from_millis(read_timeout))
+        .host_deadline(args.host_deadline.map(Duration::from_millis))
+        .retries(args.retries)
+        .workers(workers)
+        .adaptive(!args.no_adaptive)
+        .rate(args.rate)
+        .subnet_rate(args.subnet_rate)
+        .max_finds(max_finds)
+        .method(args.method.clone())
+        .paths(args.paths.clone())
+        .content_match(args.content_match.clone())
+        .match_expr(args.match_expr.clone())
+        .max_body(args.max_body)
+        .post_body(args.post_body.clone())
+        .ports(ports)
+        .scheme(scheme)
+        .cert_verification(cert_verification)
+        .retry_untrusted(args.retry_untrusted)
+        .cert_match(args.cert_match)
+        .cert_sha256(args.cert_sha256.clone())
+        .spki_sha256(args.spki_sha256.clone())
+        .two_phase(args.two_phase)
+        .verbose(args.verbose);
+    for rule in args.header_match.iter().flatten() {
+        config = config.header_match(rule.clone());
+    }
+    for header in args.headers.iter().flatten() {
+        config = config.header(header.clone());
+    }
+    // Without --status-code the builder picks the default (202 unless other rules are given)
+    if let Some(ref codes) = args.status_code {
+        config = config.status_codes(Some(codes.clone()));
+    }
+    if let Some(rules) = baseline_rules {
+        config = config.matcher(rules);
+    }
+    if let Some(rule) = similar_rule {
+        config = config.matcher(rule);
+    }
+    let mut config = config.build();
+    let status_codes = config.status_codes.clone();
+    let config_hash = checkpoint_fingerprint(&config, &args, min_similarity);
+
+    // Load the checkpoint of an interrupted scan
+    let resumed = match args.resume {
+        Some(ref path) => match Checkpoint::load(path) {
+            Ok(checkpoint) if checkpoint.config_hash != config_hash => {
+                eprintln!(
+                    "{} Checkpoint {} was written with a different domain or match configuration",
+                    "✗".red(),
+                    path.display()
+                );
+                std::process::exit(1);
+            }
+            Ok(checkpoint) => Some(checkpoint),
+            Err(e) => {
+                eprintln!(
+                    "{} Failed to load checkpoint {}: {}",
+                    "✗".red(),
+                    path.display(),
+                    e
+                );
+                std::process::exit(1);
+            }
+        },
+        None => None,
+    };
+
+    // Hits carried over from the checkpoint count against the match limit
+    let mut resumed_hits = resumed
+        .as_ref()
+        .map_or_else(Vec::new, |checkpoint| checkpoint.hits.clone());
+    if let Some(limit) = max_finds {
+        resumed_hits.truncate(limit as usize);
+        config.max_finds = Some(limit - resumed_hits.len() as u64);
+    }
+
+    let scanner = match Scanner::new(config) {
         Ok(s) => s,
         Err(e) => {
             eprintln!("{} Failed to create scanner: {}", "✗".red(), e);
@@ -616,33 +1150,87 @@ async fn main() {
         }
     };
 
-    // Handle single IP scan
-    if let Some(single_ip) = args.single_ip {
-        println!(
-            "{} Scanning single IP: {}:{}",
-            "➤".bright_green(),
-            single_ip,
-            args.port
-        );
+    // Open the results file up front so a bad path fails before scanning
+    let writer = match args.output {
+        Some(ref path) => match ResultWriter::create(path, args.format) {
+            Ok(writer) => Some(writer),
+            Err(e) => {
+                eprintln!(
+                    "{} Failed to create output file {}: {}",
+                    "✗".red(),
+                    path.display(),
+                    e
+                );
+                std::process::exit(1);
+            }
+        },
+        None => None,
+    };
 
-        match single_ip.parse::<Ipv4Addr>() {
-            Ok(ip) => {
-                if let Some((found, info)) = scanner.scan_ip(ip).await {
-                    println!("{} {} - {}", "✓".green(), found, info);
-                } else {
-                    println!("{} No matching response from {}", "✗".red(), single_ip);
+    // Ctrl-C stops dispatching new targets and lets in-flight ones finish;
+    // a second Ctrl-C exits immediately
+    let cancel = CancellationToken::new();
+    {
+        let cancel = cancel.clone();
+        tokio::spawn(async move {
+            if tokio::signal::ctrl_c().await.is_ok() {
+                cancel.cancel();
+                eprintln!(
+                    "\n{} Interrupted - finishing in-flight requests (Ctrl-C again to abort)...",
+                    "⚠".bright_yellow()
+                );
+                if tokio::signal::ctrl_c().await.is_ok() {
+                    std::process::exit(130);
                 }
             }
+        });
+    }
+
+    // Handle single IP scan
+    if let Some(ref single_ip) = args.single_ip {
+        let ip = match single_ip.parse::<IpAddr>() {
+            Ok(ip) => ip,
             Err(e) => {
                 eprintln!("{} Invalid IP address: {}", "✗".red(), e);
                 std::process::exit(1);
             }
+        };
+        println!(
+            "{} Scanning single IP: {} (port(s) {})",
+            "➤".bright_green(),
+            ip,
+            args.ports
+        );
+
+        let mut view = ScanView {
+            ranges: &[],
+            ports: &args.ports,
+            two_phase: false,
+            single_ip: true,
+            save_open: None,
+            writer,
+        };
+        let targets = args.ports.iter().map(|port| SocketAddr::new(ip, port));
+        let summary = run_scan(
+            scanner,
+            Targets::Endpoints(targets.collect()),
+            cancel,
+            &mut view,
+        )
+        .await;
+        if summary.hits.is_empty() {
+            println!("{} No matching response from {}", "✗".red(), single_ip);
         }
+        view.finish_output();
         return;
     }
 
-    // Get IP ranges to scan - priority: file > cli args > error
-    let ip_ranges = if let Some(file_path) = args.ip_file {
+    // Get IP ranges to scan - priority: checkpoint > file > cli args > error
+    let ip_ranges = if let Some(ref checkpoint) = resumed {
+        checkpoint.ranges.clone()
+    } else if open_endpoints.is_some() {
+        Vec::new()
+    } else if let Some(file_path) = args.ip_file {
         // Load from file
         match load_ip_ranges_from_file(&file_path) {
             Ok(ranges) => ranges,
@@ -660,7 +1248,7 @@ async fn main() {
         eprintln!();
         eprintln!("Please provide IP ranges using one of these methods:");
         eprintln!("  1. File:       --ip-file ips.txt");
-        eprintln!("  2. CLI args:   --ranges 35.207.0.0/16,10.0.0.0/24");
+        eprintln!("  2. CLI args:   --ranges 35.207.0.0/16,10.0.0.0/24,2001:db8::/120");
         eprintln!("  3. Single IP:  --single-ip 35.207.76.249");
         eprintln!();
         eprintln!("Example: octointel example.com --ip-file ips.txt");
@@ -668,6 +1256,22 @@ async fn main() {
         std::process::exit(1);
     };
 
+    // Parse every range up front so checkpoint positions refer to a fixed list
+    let ip_ranges: Vec<IpNetwork> = ip_ranges
+        .iter()
+        .filter_map(|range| match parse_ip_range(range) {
+            Ok(network) => Some(network),
+            Err(e) => {
+                eprintln!("{} Failed to parse range {}: {}", "✗".red(), range, e);
+                None
+            }
+        })
+        .collect();
+    if ip_ranges.is_empty() && open_endpoints.is_none() {
+        eprintln!("{} No valid IP ranges to scan", "✗".red());
+        std::process::exit(1);
+    }
+
     // Print scan configuration
     println!(
         "\n{}\n⚙ Scan Configuration:\n{}",
@@ -677,30 +1281,132 @@ async fn main() {
     println!(
         "  {} Target domain: {}",
         "→".bright_cyan(),
-        args.domain.bright_yellow()
-    );
-    println!(
-        "  {} HTTP method: {}",
-        "→".bright_cyan(),
-        args.method.bright_yellow()
-    );
-    println!(
-        "  {} Port: {}",
-        "→".bright_cyan(),
-        args.port.to_string().bright_yellow()
-    );
-    println!(
-        "  {} Target status: {}",
-        "→".bright_cyan(),
-        args.status_code.to_string().bright_yellow()
+        domain.bright_yellow()
     );
-
-    if let Some(ref content) = args.content_match {
+    let cert_mode = args.cert_match || args.cert_sha256.is_some() || args.spki_sha256.is_some();
+    if cert_mode {
         println!(
-            "  {} Content match: {}",
+            "  {} Match mode: {} (SNI: {})",
             "→".bright_cyan(),
-            content.bright_yellow()
+            "TLS certificate".bright_yellow(),
+            domain
         );
+    } else {
+        println!(
+            "  {} HTTP method: {}",
+            "→".bright_cyan(),
+            args.method.bright_yellow()
+        );
+        println!(
+            "  {} Path(s): {}",
+            "→".bright_cyan(),
+            args.paths.join(", ").bright_yellow()
+        );
+    }
+    if open_endpoints.is_none() {
+        println!(
+            "  {} Port(s): {}",
+            "→".bright_cyan(),
+            args.ports.to_string().bright_yellow()
+        );
+    }
+    let may_use_tls = match open_endpoints {
+        Some(ref endpoints) => endpoints
+            .iter()
+            .any(|e| scheme.tls_for_port(e.port()) != Some(false)),
+        None => args
+            .ports
+            .iter()
+            .any(|port| scheme.tls_for_port(port) != Some(false)),
+    };
+    if !cert_mode && !may_use_tls {
+        println!("  {} Scheme: {}", "→".bright_cyan(), "http".bright_yellow());
+    } else if !cert_mode {
+        let verification = if args.insecure {
+            "skipped".to_string()
+        } else if let Some(ref ca_file) = args.ca_file {
+            format!("CA file {}", ca_file.display())
+        } else if args.retry_untrusted {
+            "system roots, untrusted certificates retried without".to_string()
+        } else {
+            "system roots".to_string()
+        };
+        let scheme_label = match scheme {
+            Scheme::Auto => "auto (80 http, 443 https, other ports detected)",
+            _ => "https",
+        };
+        println!(
+            "  {} Scheme: {} (SNI: {}, certificate verification: {})",
+            "→".bright_cyan(),
+            scheme_label.bright_yellow(),
+            domain,
+            verification
+        );
+    }
+    if cert_mode {
+        if args.cert_match {
+            println!(
+                "  {} Certificate names: must cover {}",
+                "→".bright_cyan(),
+                domain.bright_yellow()
+            );
+        }
+        if let Some(ref sha256) = args.cert_sha256 {
+            println!(
+                "  {} Certificate SHA-256: {}",
+                "→".bright_cyan(),
+                sha256.bright_yellow()
+            );
+        }
+        if let Some(ref spki) = args.spki_sha256 {
+            println!(
+                "  {} SPKI SHA-256: {}",
+                "→".bright_cyan(),
+                spki.bright_yellow()
+            );
+        }
+    } else {
+        if let Some(ref status_codes) = status_codes {
+            println!(
+                "  {} Target status: {}",
+                "→".bright_cyan(),
+                status_codes.to_string().bright_yellow()
+            );
+        }
+
+        if let Some(ref content) = args.content_match {
+            println!(
+                "  {} Content match: {} (first {} body bytes)",
+                "→".bright_cyan(),
+                content.bright_yellow(),
+                args.max_body
+            );
+        }
+
+        // Show the expression as parsed, so operator precedence is visible
+        if let Some(ref expr) = args.match_expr {
+            let parsed = matcher::parse(expr).map_or_else(|_| expr.clone(), |m| m.describe());
+            println!("  {} Match: {}", "→".bright_cyan(), parsed.bright_yellow());
+        }
+
+        if let (Some(ref source), Some(ref rule)) = (&args.similar_to, &similar_desc) {
+            println!(
+                "  {} Similar to: {} ({})",
+                "→".bright_cyan(),
+                source.bright_yellow(),
+                rule
+            );
+        }
+    }
+
+    if let Some(ref header_matches) = args.header_match {
+        for rule in header_matches {
+            println!(
+                "  {} Header match: {}",
+                "→".bright_cyan(),
+                rule.bright_yellow()
+            );
+        }
     }
 
     if let Some(ref headers) = args.headers {
@@ -711,37 +1417,144 @@ async fn main() {
         );
     }
 
-    println!("  {} IP ranges: {}", "→".bright_cyan(), ip_ranges.len());
-    println!("  {} Concurrent workers: {}", "→".bright_cyan(), workers);
-    println!("  {} Timeout: {}ms", "→".bright_cyan(), timeout);
-
-    let start_time = Instant::now();
-    let mut all_found_ips = Vec::new();
+    if let Some(ref path) = args.output {
+        println!(
+            "  {} Output: {} ({})",
+            "→".bright_cyan(),
+            path.display().to_string().bright_yellow(),
+            args.format
+        );
+    }
 
-    // Scan each range
-    for range in &ip_ranges {
-        let found = scanner.scan_range(range, args.stop_on_find).await;
-        all_found_ips.extend(found.clone());
+    if let Some(ref endpoints) = open_endpoints {
+        println!(
+            "  {} Open endpoints: {} (from {})",
+            "→".bright_cyan(),
+            endpoints.len(),
+            args.open_from.as_deref().unwrap_or(Path::new("")).display()
+        );
+    } else {
+        println!("  {} IP ranges: {}", "→".bright_cyan(), ip_ranges.len());
+    }
+    if args.two_phase {
+        println!(
+            "  {} Mode: two-phase (TCP discovery, then probes on open endpoints)",
+            "→".bright_cyan()
+        );
+    }
+    if args.no_adaptive {
+        println!("  {} Concurrent workers: {}", "→".bright_cyan(), workers);
+    } else {
+        println!(
+            "  {} Concurrent workers: adaptive, up to {}",
+            "→".bright_cyan(),
+            workers
+        );
+    }
+    println!(
+        "  {} Timeout: connect {}ms, read {}ms",
+        "→".bright_cyan(),
+        connect_timeout,
+        read_timeout
+    );
+    if let Some(deadline) = args.host_deadline {
+        println!("  {} Host deadline: {}ms", "→".bright_cyan(), deadline);
+    }
+    if args.retries > 0 {
+        println!(
+            "  {} Retries: {} (transient errors only)",
+            "→".bright_cyan(),
+            args.retries
+        );
+    }
+    if let Some(rate) = args.rate {
+        println!("  {} Rate limit: {} conn/s", "→".bright_cyan(), rate);
+    }
+    if let Some(rate) = args.subnet_rate {
+        println!(
+            "  {} Per-subnet rate limit: {} conn/s per /24 (IPv6: /64)",
+            "→".bright_cyan(),
+            rate
+        );
+    }
+    match max_finds {
+        Some(1) => println!("  {} Stop: after first match", "→".bright_cyan()),
+        Some(limit) => println!("  {} Stop: after {} matches", "→".bright_cyan(), limit),
+        None => println!("  {} Stop: never (exhaustive scan)", "→".bright_cyan()),
+    }
 
-        // Stop if we found IPs and stop_on_find is enabled
-        if args.stop_on_find && !found.is_empty() {
+    let resume_position = match resumed {
+        Some(ref checkpoint) => {
             println!(
-                "\n{} Found backend IP(s) - stopping all remaining scans\n",
-                "⚠".bright_yellow()
+                "\n{} Resuming from checkpoint: {} target(s) already scanned, {} hit(s) so far",
+                "ℹ".bright_blue(),
+                checkpoint.position,
+                checkpoint.hits.len()
             );
-            break;
+            checkpoint.position
         }
+        None => 0,
+    };
+
+    let mut view = ScanView {
+        ranges: &ip_ranges,
+        ports: &args.ports,
+        two_phase: args.two_phase,
+        single_ip: false,
+        save_open: args.save_open.as_deref(),
+        writer,
+    };
+    for hit in &resumed_hits {
+        view.record_hit(hit);
+    }
+
+    let targets = match open_endpoints {
+        // Second phase only, on endpoints from an earlier discovery run
+        Some(endpoints) => Targets::Endpoints(endpoints),
+        // All ranges in one pipeline (two-phase scans discover first)
+        None => Targets::Ranges {
+            ranges: ip_ranges.clone(),
+            skip: resume_position,
+        },
+    };
+    let summary = run_scan(scanner, targets, cancel, &mut view).await;
+
+    // Stop if the match limit was reached
+    if summary.limit_reached {
+        println!(
+            "\n{} Match limit reached - stopping all remaining scans\n",
+            "⚠".bright_yellow()
+        );
     }
 
-    let elapsed = start_time.elapsed();
+    let interrupted = summary.cancelled;
 
     println!(
-        "\n{}\n{} Scan completed in {:.2}s\n{}",
+        "\n{}\n{} Scan {} in {:.2}s\n{}",
         "=".repeat(60).bright_cyan(),
-        "✓".bright_green(),
-        elapsed.as_secs_f64(),
+        if interrupted {
+            "⚠".bright_yellow()
+        } else {
+            "✓".bright_green()
+        },
+        if interrupted {
+            "interrupted"
+        } else {
+            "completed"
+        },
+        summary.elapsed.as_secs_f64(),
         "=".repeat(60).bright_cyan()
     );
+    println!("{} Stats: {}", "ℹ".bright_blue(), summary.stats);
+    if let Some(ref ranges) = summary.ranges {
+        for hit in &resumed_hits {
+            ranges.found_ip(hit.ip);
+        }
+        print_range_summary(ranges, args.two_phase);
+    }
+
+    let mut all_found_ips = resumed_hits;
+    all_found_ips.extend(summary.hits);
 
     if all_found_ips.is_empty() {
         println!("{} No matching IPs found", "✗".red());
@@ -751,8 +1564,51 @@ async fn main() {
             "✓".bright_green(),
             all_found_ips.len()
         );
-        for (ip, info) in all_found_ips {
-            println!("  {} {} - {}", "→".bright_cyan(), ip.bright_yellow(), info);
+        for hit in &all_found_ips {
+            println!(
+                "  {} {} - {}",
+                "→".bright_cyan(),
+                hit.endpoint().to_string().bright_yellow(),
+                hit.summary()
+            );
+        }
+    }
+
+    view.finish_output();
+    if let Some(ref path) = args.output {
+        println!(
+            "{} Results written to {}",
+            "ℹ".bright_blue(),
+            path.display()
+        );
+    }
+
+    // Checkpoints track positions in the range list, so they only cover single-phase range scans
+    if interrupted && (args.two_phase || args.open_from.is_some()) {
+        println!(
+            "{} No checkpoint written: two-phase and --open-from scans can't be resumed",
+            "ℹ".bright_blue()
+        );
+        std::process::exit(130);
+    }
+
+    if interrupted {
+        let checkpoint = Checkpoint::new(
+            config_hash,
+            domain.clone(),
+            ip_ranges.iter().map(|n| n.to_string()).collect(),
+            summary.position,
+            all_found_ips,
+        );
+        match checkpoint.save(&args.checkpoint) {
+            Ok(()) => println!(
+                "{} Checkpoint written to {} - continue with: --resume {}",
+                "ℹ".bright_blue(),
+                args.checkpoint.display(),
+                args.checkpoint.display()
+            ),
+            Err(e) => eprintln!("{} Failed to write checkpoint: {}", "✗".red(), e),
         }
+        std::process::exit(130);
     }
 }
@@ -0,0 +1,812 @@
+use crate::http::{HttpResponse, StatusSet};
+use crate::simhash;
+use crate::tls::{self, CertMatcher};
+use base64::Engine;
+use regex::Regex;
+use sha2::{Digest, Sha256};
+use std::fmt;
+use std::io::Cursor;
+
+/// How alike a body's simhash must be to the reference by default (share of equal bits)
+pub const SIMHASH_MIN_SIMILARITY: f64 = 0.8;
+
+/// What a matcher looks at: one parsed HTTP response and how it arrived
+pub struct MatchInput<'a> {
+    /// Status line, headers and (if any matcher needs it) the body
+    pub response: &'a HttpResponse,
+    /// DER-encoded leaf certificate, when the response came over TLS
+    pub certificate: Option<&'a [u8]>,
+}
+
+/// A rule deciding whether a response comes from the origin being looked for
+///
+/// Built-in matchers cover status, body and header regexes, body hashes and
+/// simhashes, favicon hashes, page titles and certificate names; [`AndMatcher`], [`OrMatcher`] and
+/// [`NotMatcher`] combine them. Implement the trait to plug custom rules
+/// into [`ScannerConfig::matchers`](crate::ScannerConfig::matchers).
+pub trait Matcher: fmt::Debug + Send + Sync {
+    /// Check a response
+    ///
+    /// # Returns
+    /// * `Some(labels)` - The response matched; `labels` names the rules that
+    ///   made it match (reported in the hit's `matched` field)
+    /// * `None` - The response did not match
+    fn evaluate(&self, input: &MatchInput<'_>) -> Option<Vec<String>>;
+
+    /// The rule in the expression syntax accepted by [`parse`]
+    fn describe(&self) -> String;
+
+    /// Whether the rule looks at the body (otherwise only status line and headers are read)
+    fn needs_body(&self) -> bool {
+        false
+    }
+}
+
+/// Shared matchers (e.g. [`ScannerConfig::matchers`](crate::ScannerConfig::matchers)) match like the inner one
+impl<M: Matcher + ?Sized> Matcher for std::sync::Arc<M> {
+    fn evaluate(&self, input: &MatchInput<'_>) -> Option<Vec<String>> {
+        (**self).evaluate(input)
+    }
+
+    fn describe(&self) -> String {
+        (**self).describe()
+    }
+
+    fn needs_body(&self) -> bool {
+        (**self).needs_body()
+    }
+}
+
+/// Status code is in a set
+#[derive(Debug)]
+pub struct StatusMatcher(pub StatusSet);
+
+impl Matcher for StatusMatcher {
+    fn evaluate(&self, input: &MatchInput<'_>) -> Option<Vec<String>> {
+        self.0
+            .contains(input.response.status)
+            .then(|| vec!["status".to_string()])
+    }
+
+    fn describe(&self) -> String {
+        format!("status={}", self.0)
+    }
+}
+
+/// Regex over the body, or over headers and body (`--content-match`)
+#[derive(Debug)]
+pub struct BodyMatcher {
+    regex: Regex,
+    include_head: bool,
+}
+
+impl BodyMatcher {
+    /// Match the body only
+    pub fn body(regex: Regex) -> Self {
+        Self {
+            regex,
+            include_head: false,
+        }
+    }
+
+    /// Match the status line and headers as well as the body
+    pub fn content(regex: Regex) -> Self {
+        Self {
+            regex,
+            include_head: true,
+        }
+    }
+
+    /// Rule name: "content" when headers are searched too, otherwise "body"
+    fn label(&self) -> &'static str {
+        if self.include_head {
+            "content"
+        } else {
+            "body"
+        }
+    }
+}
+
+impl Matcher for BodyMatcher {
+    fn evaluate(&self, input: &MatchInput<'_>) -> Option<Vec<String>> {
+        let response = input.response;
+        let matched = (self.include_head && self.regex.is_match(&response.head_text()))
+            || self.regex.is_match(&response.body_text());
+        matched.then(|| vec![self.label().to_string()])
+    }
+
+    fn describe(&self) -> String {
+        format!("{}~{}", self.label(), quote(self.regex.as_str()))
+    }
+
+    fn needs_body(&self) -> bool {
+        true
+    }
+}
+
+/// Some header with the given name (case-insensitive) has a value matching a regex
+#[derive(Debug)]
+pub struct HeaderMatcher {
+    name: String,
+    regex: Regex,
+}
+
+impl HeaderMatcher {
+    pub fn new(name: impl Into<String>, regex: Regex) -> Self {
+        Self {
+            name: name.into(),
+            regex,
+        }
+    }
+}
+
+impl Matcher for HeaderMatcher {
+    fn evaluate(&self, input: &MatchInput<'_>) -> Option<Vec<String>> {
+        input
+            .response
+            .headers
+            .iter()
+            .any(|(n, v)| n.eq_ignore_ascii_case(&self.name) && self.regex.is_match(v))
+            .then(|| vec![format!("header:{}", self.name)])
+    }
+
+    fn describe(&self) -> String {
+        format!("header:{}~{}", self.name, quote(self.regex.as_str()))
+    }
+}
+
+/// SHA-256 of the body (as read, up to the body limit) equals a known digest
+#[derive(Debug)]
+pub struct BodyHashMatcher([u8; 32]);
+
+impl BodyHashMatcher {
+    /// Create a matcher from a hex digest (colons allowed)
+    pub fn new(sha256: &str) -> Result<Self, Box<dyn std::error::Error>> {
+        Ok(Self(tls::parse_sha256_hex(sha256)?))
+    }
+}
+
+impl Matcher for BodyHashMatcher {
+    fn evaluate(&self, input: &MatchInput<'_>) -> Option<Vec<String>> {
+        (Sha256::digest(&input.response.body).as_slice() == self.0)
+            .then(|| vec!["body-hash".to_string()])
+    }
+
+    fn describe(&self) -> String {
+        let hex: String = self.0.iter().map(|b| format!("{:02x}", b)).collect();
+        format!("body-hash={}", hex)
+    }
+
+    fn needs_body(&self) -> bool {
+        true
+    }
+}
+
+/// The body's simhash is at least `min_similarity` alike a reference page's
+///
+/// Tolerates the small differences (nonces, CSRF tokens, timestamps) that
+/// make exact body hashes useless for dynamic pages. The hit label carries
+/// the score, e.g. `simhash:0.94`.
+#[derive(Debug)]
+pub struct SimhashMatcher {
+    hash: u64,
+    min_similarity: f64,
+}
+
+impl SimhashMatcher {
+    /// Match bodies similar to a page with a known simhash
+    ///
+    /// # Arguments
+    /// * `hash` - Simhash of the reference page
+    /// * `min_similarity` - Share of equal bits required, from 0.0 to 1.0
+    ///   (see [`SIMHASH_MIN_SIMILARITY`])
+    pub fn new(hash: u64, min_similarity: f64) -> Self {
+        Self {
+            hash,
+            min_similarity,
+        }
+    }
+
+    /// Match bodies similar to a reference body
+    pub fn similar_to(body: &str, min_similarity: f64) -> Self {
+        Self::new(simhash::simhash(&simhash::features(body)), min_similarity)
+    }
+
+    /// Simhash of the reference page
+    pub fn hash(&self) -> u64 {
+        self.hash
+    }
+}
+
+impl Matcher for SimhashMatcher {
+    fn evaluate(&self, input: &MatchInput<'_>) -> Option<Vec<String>> {
+        let features = simhash::features(&input.response.body_text());
+        let score = simhash::similarity(simhash::simhash(&features), self.hash);
+        (score >= self.min_similarity).then(|| vec![format!("simhash:{:.2}", score)])
+    }
+
+    fn describe(&self) -> String {
+        if self.min_similarity == SIMHASH_MIN_SIMILARITY {
+            format!("simhash={:016x}", self.hash)
+        } else {
+            format!("simhash={:016x}@{}", self.hash, self.min_similarity)
+        }
+    }
+
+    fn needs_body(&self) -> bool {
+        true
+    }
+}
+
+/// The body, taken as a favicon, has a known Shodan-style favicon hash
+///
+/// Only meaningful when probing the favicon itself (`--path /favicon.ico`).
+#[derive(Debug)]
+pub struct FaviconHashMatcher(pub i32);
+
+impl Matcher for FaviconHashMatcher {
+    fn evaluate(&self, input: &MatchInput<'_>) -> Option<Vec<String>> {
+        (!input.response.body.is_empty() && favicon_hash(&input.response.body) == self.0)
+            .then(|| vec!["favicon-hash".to_string()])
+    }
+
+    fn describe(&self) -> String {
+        format!("favicon-hash={}", self.0)
+    }
+
+    fn needs_body(&self) -> bool {
+        true
+    }
+}
+
+/// The page's `<title>` matches a regex
+#[derive(Debug)]
+pub struct TitleMatcher(pub Regex);
+
+impl Matcher for TitleMatcher {
+    fn evaluate(&self, input: &MatchInput<'_>) -> Option<Vec<String>> {
+        let title = page_title(&input.response.body_text())?;
+        self.0.is_match(&title).then(|| vec!["title".to_string()])
+    }
+
+    fn describe(&self) -> String {
+        format!("title~{}", quote(self.0.as_str()))
+    }
+
+    fn needs_body(&self) -> bool {
+        true
+    }
+}
+
+/// The TLS certificate's SAN list (or CN, without SANs) covers a name
+///
+/// Never matches responses that came over plain HTTP.
+#[derive(Debug)]
+pub struct CertSanMatcher {
+    name: String,
+    cert: CertMatcher,
+}
+
+impl CertSanMatcher {
+    pub fn new(name: &str) -> Result<Self, Box<dyn std::error::Error>> {
+        Ok(Self {
+            name: name.to_string(),
+            cert: CertMatcher::new(name, true, None, None)?,
+        })
+    }
+}
+
+impl Matcher for CertSanMatcher {
+    fn evaluate(&self, input: &MatchInput<'_>) -> Option<Vec<String>> {
+        self.cert.check(input.certificate?).map(|rule| vec![rule])
+    }
+
+    fn describe(&self) -> String {
+        format!("cert-san={}", self.name)
+    }
+}
+
+/// Every inner matcher must match; an empty list matches everything
+#[derive(Debug)]
+pub struct AndMatcher(pub Vec<Box<dyn Matcher>>);
+
+impl Matcher for AndMatcher {
+    fn evaluate(&self, input: &MatchInput<'_>) -> Option<Vec<String>> {
+        let mut labels = Vec::new();
+        for matcher in &self.0 {
+            labels.extend(matcher.evaluate(input)?);
+        }
+        Some(labels)
+    }
+
+    fn describe(&self) -> String {
+        join(&self.0, " and ")
+    }
+
+    fn needs_body(&self) -> bool {
+        self.0.iter().any(|m| m.needs_body())
+    }
+}
+
+/// At least one inner matcher must match (the first one that does is reported)
+#[derive(Debug)]
+pub struct OrMatcher(pub Vec<Box<dyn Matcher>>);
+
+impl Matcher for OrMatcher {
+    fn evaluate(&self, input: &MatchInput<'_>) -> Option<Vec<String>> {
+        self.0.iter().find_map(|matcher| matcher.evaluate(input))
+    }
+
+    fn describe(&self) -> String {
+        join(&self.0, " or ")
+    }
+
+    fn needs_body(&self) -> bool {
+        self.0.iter().any(|m| m.needs_body())
+    }
+}
+
+/// The inner matcher must not match
+#[derive(Debug)]
+pub struct NotMatcher(pub Box<dyn Matcher>);
+
+impl Matcher for NotMatcher {
+    fn evaluate(&self, input: &MatchInput<'_>) -> Option<Vec<String>> {
+        match self.0.evaluate(input) {
+            Some(_) => None,
+            None => Some(vec![format!("not {}", self.0.describe())]),
+        }
+    }
+
+    fn describe(&self) -> String {
+        format!("not {}", group(&*self.0))
+    }
+
+    fn needs_body(&self) -> bool {
+        self.0.needs_body()
+    }
+}
+
+/// Describe matchers joined by an operator, parenthesising nested combinations
+fn join(matchers: &[Box<dyn Matcher>], operator: &str) -> String {
+    matchers
+        .iter()
+        .map(|m| group(&**m))
+        .collect::<Vec<_>>()
+        .join(operator)
+}
+
+/// Describe a matcher, in parentheses if it combines several rules
+fn group(matcher: &dyn Matcher) -> String {
+    let text = matcher.describe();
+    if text.contains(" and ") || text.contains(" or ") {
+        format!("({})", text)
+    } else {
+        text
+    }
+}
+
+/// Quote a value for the expression syntax if it would not survive unquoted
+fn quote(value: &str) -> String {
+    if value.is_empty() || value.contains(|c: char| c.is_whitespace() || "()\"'".contains(c)) {
+        if value.contains('"') {
+            format!("'{}'", value)
+        } else {
+            format!("\"{}\"", value)
+        }
+    } else {
+        value.to_string()
+    }
+}
+
+/// Text of the first `<title>` element, with whitespace collapsed and basic entities decoded
+pub fn page_title(html: &str) -> Option<String> {
+    let lower = html.to_ascii_lowercase();
+    let open = lower.find("<title")?;
+    let start = open + lower[open..].find('>')? + 1;
+    let end = start + lower[start..].find("</title")?;
+
+    let title = html[start..end]
+        .split_whitespace()
+        .collect::<Vec<_>>()
+        .join(" ")
+        .replace("&lt;", "<")
+        .replace("&gt;", ">")
+        .replace("&quot;", "\"")
+        .replace("&#39;", "'")
+        .replace("&amp;", "&");
+    Some(title)
+}
+
+/// Favicon hash as used by Shodan (`http.favicon.hash`)
+///
+/// MurmurHash3 (x86, 32-bit, seed 0) of the icon's base64 encoding with a
+/// newline after every 76 characters and at the end, as a signed integer.
+pub fn favicon_hash(icon: &[u8]) -> i32 {
+    let encoded = base64::engine::general_purpose::STANDARD.encode(icon);
+    let mut wrapped = String::with_capacity(encoded.len() + encoded.len() / 76 + 1);
+    for line in encoded.as_bytes().chunks(76) {
+        wrapped.push_str(std::str::from_utf8(line).expect("base64 is ASCII"));
+        wrapped.push('\n');
+    }
+    let hash = murmur3::murmur3_32(&mut Cursor::new(wrapped.as_bytes()), 0)
+        .expect("reading from memory cannot fail");
+    hash as i32
+}
+
+/// Parse a minimum similarity: a share from 0.0 to 1.0
+pub fn parse_similarity(value: &str) -> Result<f64, String> {
+    match value.parse::<f64>() {
+        Ok(ratio) if (0.0..=1.0).contains(&ratio) => Ok(ratio),
+        _ => Err(format!(
+            "invalid similarity '{}': expected a number from 0 to 1, e.g. 0.8",
+            value
+        )),
+    }
+}
+
+/// Token of a match expression
+#[derive(Debug, PartialEq)]
+enum Token {
+    Open,
+    Close,
+    /// A keyword or rule; `quoted` words are never keywords
+    Word {
+        text: String,
+        quoted: bool,
+    },
+}
+
+/// Split an expression into parentheses and words, honouring quotes
+fn tokenize(expr: &str) -> Result<Vec<Token>, String> {
+    let mut tokens = Vec::new();
+    let mut chars = expr.chars().peekable();
+
+    while let Some(&c) = chars.peek() {
+        match c {
+            _ if c.is_whitespace() => {
+                chars.next();
+            }
+            '(' => {
+                chars.next();
+                tokens.push(Token::Open);
+            }
+            ')' => {
+                chars.next();
+                tokens.push(Token::Close);
+            }
+            _ => {
+                let mut text = String::new();
+                let mut quoted = false;
+                while let Some(&c) = chars.peek() {
+                    if c.is_whitespace() || c == '(' || c == ')' {
+                        break;
+                    }
+                    chars.next();
+                    if c == '"' || c == '\'' {
+                        quoted = true;
+                        loop {
+                            match chars.next() {
+                                Some(q) if q == c => break,
+                                Some(other) => text.push(other),
+                                None => return Err(format!("unterminated {} quote", c)),
+                            }
+                        }
+                    } else {
+                        text.push(c);
+                    }
+                }
+                tokens.push(Token::Word { text, quoted });
+            }
+        }
+    }
+
+    Ok(tokens)
+}
+
+/// Recursive-descent parser over the tokens of a match expression
+struct Parser {
+    tokens: Vec<Token>,
+    position: usize,
+}
+
+impl Parser {
+    /// Whether the next token is the given (unquoted) keyword; consumes it if so
+    fn keyword(&mut self, keyword: &str) -> bool {
+        match self.tokens.get(self.position) {
+            Some(Token::Word {
+                text,
+                quoted: false,
+            }) if text.eq_ignore_ascii_case(keyword) => {
+                self.position += 1;
+                true
+            }
+            _ => false,
+        }
+    }
+
+    /// expr := and_expr ("or" and_expr)*
+    fn or_expr(&mut self) -> Result<Box<dyn Matcher>, Box<dyn std::error::Error>> {
+        let mut operands = vec![self.and_expr()?];
+        while self.keyword("or") {
+            operands.push(self.and_expr()?);
+        }
+        Ok(match operands.len() {
+            1 => operands.remove(0),
+            _ => Box::new(OrMatcher(operands)),
+        })
+    }
+
+    /// and_expr := unary ("and" unary)*
+    fn and_expr(&mut self) -> Result<Box<dyn Matcher>, Box<dyn std::error::Error>> {
+        let mut operands = vec![self.unary()?];
+        while self.keyword("and") {
+            operands.push(self.unary()?);
+        }
+        Ok(match operands.len() {
+            1 => operands.remove(0),
+            _ => Box::new(AndMatcher(operands)),
+        })
+    }
+
+    /// unary := "not" unary | "(" expr ")" | rule
+    fn unary(&mut self) -> Result<Box<dyn Matcher>, Box<dyn std::error::Error>> {
+        if self.keyword("not") {
+            return Ok(Box::new(NotMatcher(self.unary()?)));
+        }
+
+        match self.tokens.get(self.position) {
+            Some(Token::Open) => {
+                self.position += 1;
+                let inner = self.or_expr()?;
+                if self.tokens.get(self.position) != Some(&Token::Close) {
+                    return Err("missing ')'".into());
+                }
+                self.position += 1;
+                Ok(inner)
+            }
+            Some(Token::Word { text, .. }) => {
+                let rule = parse_rule(text)?;
+                self.position += 1;
+                Ok(rule)
+            }
+            Some(Token::Close) => Err("unexpected ')'".into()),
+            None => Err("expression ends where a rule was expected".into()),
+        }
+    }
+}
+
+/// Parse one rule such as `status=200`, `title~Example` or `header:Server~nginx`
+fn parse_rule(rule: &str) -> Result<Box<dyn Matcher>, Box<dyn std::error::Error>> {
+    let compile = |pattern: &str| {
+        Regex::new(pattern).map_err(|e| format!("invalid regex in '{}': {}", rule, e))
+    };
+
+    if let Some(header) = rule.strip_prefix("header:") {
+        let Some((name, pattern)) = header.split_once('~') else {
+            return Err(format!("invalid rule '{}': expected header:NAME~REGEX", rule).into());
+        };
+        if name.is_empty() {
+            return Err(format!("missing header name in '{}'", rule).into());
+        }
+        return Ok(Box::new(HeaderMatcher::new(name, compile(pattern)?)));
+    }
+
+    let split = rule.find(['~', '=']).map(|i| rule.split_at(i));
+    let matcher: Box<dyn Matcher> = match split {
+        Some(("status", value)) if value.starts_with('=') => {
+            Box::new(StatusMatcher(value[1..].parse::<StatusSet>()?))
+        }
+        Some(("body", value)) if value.starts_with('~') => {
+            Box::new(BodyMatcher::body(compile(&value[1..])?))
+        }
+        Some(("content", value)) if value.starts_with('~') => {
+            Box::new(BodyMatcher::content(compile(&value[1..])?))
+        }
+        Some(("title", value)) if value.starts_with('~') => {
+            Box::new(TitleMatcher(compile(&value[1..])?))
+        }
+        Some(("body-hash", value)) if value.starts_with('=') => {
+            Box::new(BodyHashMatcher::new(&value[1..])?)
+        }
+        Some(("cert-san", value)) if value.starts_with('=') => {
+            Box::new(CertSanMatcher::new(&value[1..])?)
+        }
+        Some(("simhash", value)) if value.starts_with('=') => {
+            let (hash, min_similarity) = match value[1..].split_once('@') {
+                Some((hash, min)) => (hash, parse_similarity(min)?),
+                None => (&value[1..], SIMHASH_MIN_SIMILARITY),
+            };
+            let hash = u64::from_str_radix(hash, 16)
+                .map_err(|_| format!("invalid simhash in '{}': expected 16 hex digits", rule))?;
+            Box::new(SimhashMatcher::new(hash, min_similarity))
+        }
+        Some(("favicon-hash", value)) if value.starts_with('=') => {
+            let hash = value[1..]
+                .parse::<i32>()
+                .map_err(|_| format!("invalid favicon hash in '{}': expected an integer", rule))?;
+            Box::new(FaviconHashMatcher(hash))
+        }
+        _ => {
+            return Err(format!(
+                "unknown rule '{}': expected status=CODES, body~REGEX, content~REGEX, \
+                 header:NAME~REGEX, title~REGEX, body-hash=SHA256, cert-san=NAME, \
+                 simhash=HEX[@SIMILARITY] or favicon-hash=N",
+                rule
+            )
+            .into())
+        }
+    };
+    Ok(matcher)
+}
+
+/// Parse a match expression
+///
+/// # Format
+/// - Rules: `status=CODES`, `body~REGEX`, `content~REGEX` (headers and body),
+///   `header:NAME~REGEX`, `title~REGEX`, `body-hash=SHA256`, `cert-san=NAME`,
+///   `simhash=HEX[@SIMILARITY]` (body similar to a page with that simhash) and
+///   `favicon-hash=N` (Shodan favicon hash of the body)
+/// - Combined with `and`, `or` and `not` (in that order of precedence from
+///   loosest to tightest: `or`, `and`, `not`) and grouped with parentheses
+/// - Values containing spaces or parentheses are quoted with `"` or `'`
+///
+/// # Example
+/// `status=200 and title~"Example Domain" and not header:Server~cloudflare`
+pub fn parse(expr: &str) -> Result<Box<dyn Matcher>, Box<dyn std::error::Error>> {
+    let mut parser = Parser {
+        tokens: tokenize(expr)?,
+        position: 0,
+    };
+    let matcher = parser.or_expr()?;
+    match parser.tokens.get(parser.position) {
+        None => Ok(matcher),
+        Some(Token::Open) => Err("unexpected '(' in match expression".into()),
+        Some(Token::Close) => Err("unexpected ')' in match expression".into()),
+        Some(Token::Word { text, .. }) => Err(format!(
+            "unexpected '{}' in match expression (rules are joined with and/or)",
+            text
+        )
+        .into()),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn response(status: u16, server: &str, body: &str) -> HttpResponse {
+        HttpResponse {
+            status,
+            reason: String::new(),
+            headers: vec![("Server".to_string(), server.to_string())],
+            body: body.as_bytes().to_vec(),
+            truncated: false,
+        }
+    }
+
+    fn matches(expr: &str, response: &HttpResponse) -> bool {
+        parse(expr)
+            .unwrap()
+            .evaluate(&MatchInput {
+                response,
+                certificate: None,
+            })
+            .is_some()
+    }
+
+    fn error(expr: &str) -> String {
+        parse(expr)
+            .expect_err("expression should not parse")
+            .to_string()
+    }
+
+    #[test]
+    fn and_binds_tighter_than_or() {
+        let matcher = parse("status=200 and body~a or body~b").unwrap();
+        assert_eq!(matcher.describe(), "(status=200 and body~a) or body~b");
+
+        let page = response(404, "nginx", "b");
+        assert!(matches("status=200 and body~a or body~b", &page));
+        assert!(!matches("status=200 and (body~a or body~b)", &page));
+    }
+
+    #[test]
+    fn not_applies_to_the_next_operand() {
+        let matcher = parse("not status=200 and body~a").unwrap();
+        assert_eq!(matcher.describe(), "not status=200 and body~a");
+
+        let page = response(200, "nginx", "a");
+        assert!(!matches("not status=200 and body~a", &page));
+        assert!(matches("not (status=200 and body~b)", &page));
+        assert!(matches("not not status=200", &page));
+    }
+
+    #[test]
+    fn parentheses_group_operands() {
+        let matcher = parse("(status=200 or status=301) and not (body~x or body~y)").unwrap();
+        assert_eq!(
+            matcher.describe(),
+            "(status=200 or status=301) and (not (body~x or body~y))"
+        );
+        assert!(matches(
+            "((status=301 or status=200)) and body~ok",
+            &response(200, "nginx", "ok")
+        ));
+    }
+
+    #[test]
+    fn quoted_values_keep_spaces() {
+        let page = response(200, "Example Server", "<title>Example Domain</title>");
+        assert!(matches(r#"title~"Example Domain""#, &page));
+        assert!(matches("header:Server~'Example Server'", &page));
+        assert!(!matches(r#"title~"Example Shop""#, &page));
+
+        // Quoted keywords are values, not operators
+        assert_eq!(parse(r#"body~"and""#).unwrap().describe(), "body~and");
+    }
+
+    #[test]
+    fn keywords_are_case_insensitive() {
+        assert_eq!(
+            parse("status=200 AND NOT body~a OR body~b")
+                .unwrap()
+                .describe(),
+            "(status=200 and not body~a) or body~b"
+        );
+    }
+
+    #[test]
+    fn unbalanced_parentheses_are_rejected() {
+        assert_eq!(error("(status=200 and body~a"), "missing ')'");
+        assert_eq!(error("status=200)"), "unexpected ')' in match expression");
+        assert_eq!(error("()"), "unexpected ')'");
+    }
+
+    #[test]
+    fn dangling_operators_are_rejected() {
+        assert_eq!(
+            error("status=200 and"),
+            "expression ends where a rule was expected"
+        );
+        assert_eq!(
+            error("status=200 or not"),
+            "expression ends where a rule was expected"
+        );
+        assert_eq!(error(""), "expression ends where a rule was expected");
+        assert!(error("status=200 body~a").starts_with("unexpected 'body~a'"));
+    }
+
+    #[test]
+    fn unknown_rules_are_rejected() {
+        assert!(error("server~nginx").starts_with("unknown rule 'server~nginx'"));
+        assert!(error("status~200").starts_with("unknown rule 'status~200'"));
+        assert!(error("header:~x").starts_with("missing header name"));
+        assert!(error("title~'('").starts_with("invalid regex"));
+        assert_eq!(error("title~\"Example"), "unterminated \" quote");
+    }
+
+    #[test]
+    fn similarity_must_be_a_share() {
+        assert_eq!(parse_similarity("0.85"), Ok(0.85));
+        assert_eq!(parse_similarity("0"), Ok(0.0));
+        assert_eq!(parse_similarity("1"), Ok(1.0));
+        for value in ["", "-0.1", "1.01", "85%", "NaN", "high"] {
+            assert!(parse_similarity(value).is_err(), "'{}' accepted", value);
+        }
+    }
+
+    #[test]
+    fn simhash_rules_round_trip() {
+        assert_eq!(
+            parse("simhash=00ff00ff00ff00ff").unwrap().describe(),
+            "simhash=00ff00ff00ff00ff"
+        );
+        assert_eq!(
+            parse("simhash=00ff00ff00ff00ff@0.9").unwrap().describe(),
+            "simhash=00ff00ff00ff00ff@0.9"
+        );
+        assert!(error("simhash=xyz").starts_with("invalid simhash"));
+        assert!(error("simhash=00ff@2").starts_with("invalid similarity"));
+    }
+}
@@ -0,0 +1,119 @@
+use crate::ScanHit;
+use clap::ValueEnum;
+use std::fmt;
+use std::fs::File;
+use std::io::{self, BufWriter, Write};
+use std::path::Path;
+
+/// File format for scan results
+#[derive(Debug, Clone, Copy, PartialEq, Eq, ValueEnum)]
+pub enum OutputFormat {
+    /// One JSON object per line
+    Jsonl,
+    /// A single JSON array
+    Json,
+    /// Comma-separated values with a header row
+    Csv,
+}
+
+impl fmt::Display for OutputFormat {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let name = self.to_possible_value().map(|v| v.get_name().to_string());
+        f.write_str(name.as_deref().unwrap_or("unknown"))
+    }
+}
+
+/// Column order for CSV output
+const CSV_HEADER: [&str; 9] = [
+    "ip",
+    "port",
+    "scheme",
+    "path",
+    "status",
+    "matched",
+    "response_time_ms",
+    "excerpt",
+    "cert_trusted",
+];
+
+/// Writes scan hits to a results file as they are found
+///
+/// Every record is flushed immediately, so a crash or kill loses nothing
+/// that was already reported. For the JSON array format only the closing
+/// bracket written by [`ResultWriter::finish`] would be missing.
+pub struct ResultWriter {
+    format: OutputFormat,
+    file: BufWriter<File>,
+    records: usize,
+}
+
+impl ResultWriter {
+    /// Create (or truncate) the results file and write any format preamble
+    pub fn create(path: &Path, format: OutputFormat) -> io::Result<Self> {
+        let mut file = BufWriter::new(File::create(path)?);
+
+        match format {
+            OutputFormat::Json => file.write_all(b"[")?,
+            OutputFormat::Csv => writeln!(file, "{}", CSV_HEADER.join(","))?,
+            OutputFormat::Jsonl => {}
+        }
+        file.flush()?;
+
+        Ok(Self {
+            format,
+            file,
+            records: 0,
+        })
+    }
+
+    /// Append one hit and flush it to disk
+    pub fn write_hit(&mut self, hit: &ScanHit) -> io::Result<()> {
+        match self.format {
+            OutputFormat::Jsonl => {
+                serde_json::to_writer(&mut self.file, hit)?;
+                self.file.write_all(b"\n")?;
+            }
+            OutputFormat::Json => {
+                let separator: &[u8] = if self.records == 0 { b"\n  " } else { b",\n  " };
+                self.file.write_all(separator)?;
+                serde_json::to_writer(&mut self.file, hit)?;
+            }
+            OutputFormat::Csv => {
+                let fields = [
+                    hit.ip.to_string(),
+                    hit.port.to_string(),
+                    hit.scheme.clone(),
+                    hit.path.clone().unwrap_or_default(),
+                    hit.status.map(|s| s.to_string()).unwrap_or_default(),
+                    hit.matched.join(";"),
+                    hit.response_time_ms.to_string(),
+                    hit.excerpt.clone(),
+                    hit.cert_trusted.map(|t| t.to_string()).unwrap_or_default(),
+                ];
+                let row: Vec<String> = fields.iter().map(|f| csv_field(f)).collect();
+                writeln!(self.file, "{}", row.join(","))?;
+            }
+        }
+
+        self.records += 1;
+        self.file.flush()
+    }
+
+    /// Write any format trailer and flush the file
+    pub fn finish(&mut self) -> io::Result<()> {
+        if self.format == OutputFormat::Json {
+            let trailer: &[u8] = if self.records == 0 { b"]\n" } else { b"\n]\n" };
+            self.file.write_all(trailer)?;
+        }
+        self.file.flush()
+    }
+}
+
+/// Quote a CSV field when it contains separators, quotes or line breaks (RFC 4180)
+fn csv_field(value: &str) -> String {
+    if value.contains([',', '"', '\n', '\r']) {
+        format!("\"{}\"", value.replace('"', "\"\""))
+    } else {
+        value.to_string()
+    }
+}
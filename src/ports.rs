@@ -0,0 +1,127 @@
+use std::collections::HashSet;
+use std::fmt;
+use std::str::FromStr;
+
+/// Ports probed on every scanned address
+///
+/// Parsed from a comma-separated list of ports (`443`) and inclusive
+/// ranges (`8080-8090`). Duplicates are dropped; the first occurrence
+/// decides the scan order.
+#[derive(Debug, Clone)]
+pub struct PortList {
+    ports: Vec<u16>,
+    spec: String,
+}
+
+impl PortList {
+    /// Ports in scan order
+    pub fn iter(&self) -> impl Iterator<Item = u16> + '_ {
+        self.ports.iter().copied()
+    }
+
+    /// Number of distinct ports
+    pub fn count(&self) -> usize {
+        self.ports.len()
+    }
+}
+
+impl FromStr for PortList {
+    type Err = String;
+
+    fn from_str(spec: &str) -> Result<Self, Self::Err> {
+        let parse_port = |port: &str| -> Result<u16, String> {
+            match port.trim().parse::<u16>() {
+                Ok(n) if n > 0 => Ok(n),
+                _ => Err(format!("invalid port '{}'", port.trim())),
+            }
+        };
+
+        let mut ports = Vec::new();
+        let mut seen = HashSet::new();
+        for part in spec.split(',').map(str::trim).filter(|p| !p.is_empty()) {
+            let (start, end) = match part.split_once('-') {
+                Some((start, end)) => (parse_port(start)?, parse_port(end)?),
+                None => {
+                    let port = parse_port(part)?;
+                    (port, port)
+                }
+            };
+            if start > end {
+                return Err(format!("invalid port range '{}'", part));
+            }
+            ports.extend((start..=end).filter(|port| seen.insert(*port)));
+        }
+
+        if ports.is_empty() {
+            return Err("no ports given".to_string());
+        }
+
+        Ok(Self {
+            ports,
+            spec: spec.split(',').map(str::trim).collect::<Vec<_>>().join(","),
+        })
+    }
+}
+
+impl FromIterator<u16> for PortList {
+    fn from_iter<I: IntoIterator<Item = u16>>(iter: I) -> Self {
+        let mut seen = HashSet::new();
+        let ports: Vec<u16> = iter.into_iter().filter(|port| seen.insert(*port)).collect();
+        let spec = ports
+            .iter()
+            .map(u16::to_string)
+            .collect::<Vec<_>>()
+            .join(",");
+        Self { ports, spec }
+    }
+}
+
+impl fmt::Display for PortList {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(&self.spec)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn ports(spec: &str) -> Vec<u16> {
+        spec.parse::<PortList>().unwrap().iter().collect()
+    }
+
+    #[test]
+    fn parses_ports_and_ranges_in_order() {
+        assert_eq!(ports("443"), vec![443]);
+        assert_eq!(
+            ports("80, 443,8000-8003"),
+            vec![80, 443, 8000, 8001, 8002, 8003]
+        );
+        assert_eq!(ports("8443-8443"), vec![8443]);
+        assert_eq!(ports("65534-65535"), vec![65534, 65535]);
+        assert_eq!(
+            "80, 443,8000-8003".parse::<PortList>().unwrap().to_string(),
+            "80,443,8000-8003"
+        );
+    }
+
+    #[test]
+    fn duplicates_keep_their_first_position() {
+        let list: PortList = "443,80,443,79-81".parse().unwrap();
+        assert_eq!(list.iter().collect::<Vec<_>>(), vec![443, 80, 79, 81]);
+        assert_eq!(list.count(), 4);
+
+        let list: PortList = [8080, 80, 8080].into_iter().collect();
+        assert_eq!(list.iter().collect::<Vec<_>>(), vec![8080, 80]);
+        assert_eq!(list.to_string(), "8080,80");
+    }
+
+    #[test]
+    fn rejects_invalid_ports() {
+        for spec in [
+            "", ",", "0", "65536", "http", "-80", "80-", "90-80", "80,,x", "1-2-3",
+        ] {
+            assert!(spec.parse::<PortList>().is_err(), "'{}' accepted", spec);
+        }
+    }
+}
@@ -0,0 +1,650 @@
+use crate::Args;
+use clap::parser::ValueSource;
+use clap::{ArgMatches, CommandFactory, ValueEnum};
+use octointel::matcher;
+use octointel::output::OutputFormat;
+use octointel::{Scheme, StatusSet};
+use serde::{Deserialize, Serialize};
+use std::fmt;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::str::FromStr;
+
+/// A scan described in a TOML file (`--config scan.toml`)
+///
+/// Every setting is optional and mirrors a command-line flag; flags given on
+/// the command line override the file. `--print-config` writes the effective
+/// settings back in this format.
+#[derive(Debug, Default, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct Profile {
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub domain: Option<String>,
+    pub targets: TargetSettings,
+    pub request: RequestSettings,
+    #[serde(rename = "match")]
+    pub matching: MatchSettings,
+    #[serde(skip_serializing_if = "TlsSettings::is_empty")]
+    pub tls: TlsSettings,
+    pub output: OutputSettings,
+    pub limits: LimitSettings,
+}
+
+/// What to scan: `[targets]`
+#[derive(Debug, Default, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct TargetSettings {
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub ranges: Option<Vec<String>>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub ip_file: Option<PathBuf>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub single_ip: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub open_from: Option<PathBuf>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub ports: Option<Spec>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub scheme: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub two_phase: Option<bool>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub save_open: Option<PathBuf>,
+}
+
+/// What to send: `[request]`
+#[derive(Debug, Default, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct RequestSettings {
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub method: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub paths: Option<Vec<String>>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub headers: Option<Vec<String>>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub post_body: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub max_body: Option<usize>,
+}
+
+/// What counts as a hit: `[match]`
+#[derive(Debug, Default, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct MatchSettings {
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub status: Option<Spec>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub content: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub headers: Option<Vec<String>>,
+    /// Match expression, as for `--match`
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub expr: Option<String>,
+    /// Derive rules from the site's public address, as for `--baseline`
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub baseline: Option<bool>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub baseline_addr: Option<String>,
+    /// Reference page file or URL, as for `--similar-to`
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub similar_to: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub similarity: Option<f64>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub cert_match: Option<bool>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub cert_sha256: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub spki_sha256: Option<String>,
+}
+
+/// Certificate verification: `[tls]`
+#[derive(Debug, Default, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct TlsSettings {
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub insecure: Option<bool>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub ca_file: Option<PathBuf>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub retry_untrusted: Option<bool>,
+}
+
+impl TlsSettings {
+    fn is_empty(&self) -> bool {
+        self.insecure.is_none() && self.ca_file.is_none() && self.retry_untrusted.is_none()
+    }
+}
+
+/// Where results go: `[output]`
+#[derive(Debug, Default, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct OutputSettings {
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub file: Option<PathBuf>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub format: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub checkpoint: Option<PathBuf>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub verbose: Option<bool>,
+}
+
+/// Speed, timeouts and when to stop: `[limits]`
+#[derive(Debug, Default, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct LimitSettings {
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub workers: Option<usize>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub adaptive: Option<bool>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub threads: Option<u32>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub current_thread: Option<bool>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub timeout: Option<u64>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub connect_timeout: Option<u64>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub read_timeout: Option<u64>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub host_deadline: Option<u64>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub retries: Option<u32>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub rate: Option<u32>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub subnet_rate: Option<u32>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub all: Option<bool>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub max_finds: Option<u64>,
+}
+
+/// A port or status list, written as a number (`443`) or a string (`"80,443,8000-8010"`)
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(untagged)]
+pub enum Spec {
+    Number(u64),
+    Text(String),
+}
+
+impl Spec {
+    /// Parse the list with the same syntax as the command line
+    fn parse<T: FromStr<Err = String>>(&self, key: &str) -> Result<T, String> {
+        self.to_string()
+            .parse()
+            .map_err(|e| format!("invalid {}: {}", key, e))
+    }
+}
+
+impl fmt::Display for Spec {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Spec::Number(n) => write!(f, "{}", n),
+            Spec::Text(text) => f.write_str(text),
+        }
+    }
+}
+
+/// Parse a clap value enum (`scheme`, `format`) from its command-line name
+fn parse_enum<T: ValueEnum>(value: &str, key: &str) -> Result<T, String> {
+    T::from_str(value, true).map_err(|_| {
+        let names: Vec<String> = T::value_variants()
+            .iter()
+            .filter_map(|v| v.to_possible_value().map(|p| p.get_name().to_string()))
+            .collect();
+        format!(
+            "invalid {} '{}' (expected one of: {})",
+            key,
+            value,
+            names.join(", ")
+        )
+    })
+}
+
+/// Check the merged settings against the command line's own rules
+///
+/// The flags given on the command line and the settings filled from the
+/// file go through clap once more, so every `conflicts_with` and `requires`
+/// of [`Args`] applies to file values too. File values are already typed
+/// and are passed as placeholders, except `checked` ones, which go through
+/// their flag's value parser as written (e.g. `rate >= 1`).
+///
+/// # Arguments
+/// * `matches` - Matches of the command line, for the flags given there
+/// * `from_file` - Ids of the settings filled from the file
+/// * `checked` - File values to validate, by id
+fn check_merged(
+    matches: &ArgMatches,
+    from_file: &[&str],
+    checked: &[(&str, String)],
+) -> Result<(), String> {
+    let checked_value = |id: &str| {
+        checked
+            .iter()
+            .find(|(checked_id, _)| *checked_id == id)
+            .map(|(_, value)| value.as_str())
+    };
+    let command = Args::command().mut_args(|arg| {
+        if arg.get_action().takes_values() && checked_value(arg.get_id().as_str()).is_none() {
+            arg.value_parser(clap::builder::StringValueParser::new())
+        } else {
+            arg
+        }
+    });
+
+    // Argument groups show up among the ids too, but only arguments go back on the line
+    let explicit = matches
+        .ids()
+        .map(|id| id.as_str())
+        .filter(|id| command.get_groups().all(|group| group.get_id() != *id))
+        .filter(|id| matches.value_source(id) == Some(ValueSource::CommandLine));
+    let mut argv = vec!["octointel".to_string()];
+    for id in explicit.chain(from_file.iter().copied()) {
+        let arg = command
+            .get_arguments()
+            .find(|arg| arg.get_id() == id)
+            .ok_or_else(|| format!("unknown setting `{}`", id))?;
+        let value = checked_value(id).unwrap_or("x");
+        match (arg.get_long(), arg.get_action().takes_values()) {
+            (Some(long), true) => argv.push(format!("--{}={}", long, value)),
+            (Some(long), false) => argv.push(format!("--{}", long)),
+            (None, _) => argv.push(value.to_string()),
+        }
+    }
+
+    // Keep clap's message up to its usage block, on one line
+    command.try_get_matches_from(argv).map(|_| ()).map_err(|e| {
+        let message = e.to_string();
+        let message: Vec<&str> = message
+            .lines()
+            .take_while(|line| !line.is_empty())
+            .map(str::trim)
+            .collect();
+        message.join(" ").trim_start_matches("error: ").to_string()
+    })
+}
+
+impl Profile {
+    /// Read and parse a profile file
+    pub fn load(path: &Path) -> Result<Self, Box<dyn std::error::Error>> {
+        let content = fs::read_to_string(path)?;
+        Ok(toml::from_str(&content)?)
+    }
+
+    /// Fill every setting not given on the command line from the profile
+    ///
+    /// # Arguments
+    /// * `args` - Parsed command line, updated in place
+    /// * `matches` - Matches the arguments came from, to tell explicit flags from defaults
+    ///
+    /// # Behavior
+    /// - A flag given on the command line always wins over the file
+    /// - Target sources (ranges, IP file, single IP, saved endpoints,
+    ///   checkpoint), stop rules (`--all`, `--max-finds`, `--stop-on-find`)
+    ///   `--scheme`/`--https` and TLS verification override the file as a
+    ///   group, so the command line never gets mixed with a conflicting file setting
+    /// - The merged settings must pass the same conflicts, requirements and
+    ///   value checks as flags would
+    pub fn apply(self, args: &mut Args, matches: &ArgMatches) -> Result<(), String> {
+        let explicit = |id: &str| matches.value_source(id) == Some(ValueSource::CommandLine);
+        let any_explicit = |ids: &[&str]| ids.iter().any(|id| explicit(id));
+
+        // Settings taken from the file, checked against the flags' rules at the end
+        let mut from_file: Vec<&str> = Vec::new();
+        let mut checked: Vec<(&str, String)> = Vec::new();
+
+        macro_rules! fill {
+            ($field:ident, $value:expr) => {
+                if !explicit(stringify!($field)) {
+                    if let Some(value) = $value {
+                        args.$field = value;
+                        from_file.push(stringify!($field));
+                    }
+                }
+            };
+        }
+        macro_rules! fill_flag {
+            ($field:ident, $value:expr) => {
+                if !explicit(stringify!($field)) {
+                    if let Some(value) = $value {
+                        args.$field = value;
+                        if value {
+                            from_file.push(stringify!($field));
+                        }
+                    }
+                }
+            };
+        }
+        macro_rules! fill_option {
+            ($field:ident, $value:expr) => {
+                if !explicit(stringify!($field)) {
+                    if let Some(value) = $value {
+                        args.$field = Some(value);
+                        from_file.push(stringify!($field));
+                    }
+                }
+            };
+        }
+        // Like `fill_option`, with the value checked by the flag's value parser
+        macro_rules! fill_checked {
+            ($field:ident, $value:expr) => {
+                if !explicit(stringify!($field)) {
+                    if let Some(value) = $value {
+                        args.$field = Some(value);
+                        from_file.push(stringify!($field));
+                        checked.push((stringify!($field), value.to_string()));
+                    }
+                }
+            };
+        }
+
+        fill_option!(domain, self.domain);
+
+        // Targets
+        let targets = self.targets;
+        if !any_explicit(&["ranges", "ip_file", "single_ip", "open_from", "resume"]) {
+            fill_option!(ranges, targets.ranges);
+            fill_option!(ip_file, targets.ip_file);
+            fill_option!(single_ip, targets.single_ip);
+            fill_option!(open_from, targets.open_from);
+        }
+        fill!(ports, targets.ports.map(|p| p.parse("ports")).transpose()?);
+        if !any_explicit(&["scheme", "https"]) {
+            if let Some(ref scheme) = targets.scheme {
+                args.scheme = parse_enum::<Scheme>(scheme, "scheme")?;
+                args.https = false;
+                from_file.push("scheme");
+            }
+        }
+        fill_flag!(two_phase, targets.two_phase);
+        fill_option!(save_open, targets.save_open);
+
+        // Request
+        let request = self.request;
+        if let Some(ref method) = request.method {
+            if !["HEAD", "GET", "POST"].contains(&method.as_str()) {
+                return Err(format!(
+                    "invalid method '{}' (expected HEAD, GET or POST)",
+                    method
+                ));
+            }
+        }
+        fill!(method, request.method);
+        fill!(paths, request.paths);
+        fill_option!(headers, request.headers);
+        fill_option!(post_body, request.post_body);
+        fill!(max_body, request.max_body);
+
+        // Match rules
+        let matching = self.matching;
+        fill_option!(
+            status_code,
+            matching
+                .status
+                .map(|s| s.parse::<StatusSet>("status"))
+                .transpose()?
+        );
+        fill_option!(content_match, matching.content);
+        fill_option!(header_match, matching.headers);
+        fill_option!(match_expr, matching.expr);
+        fill_flag!(baseline, matching.baseline);
+        fill_option!(baseline_addr, matching.baseline_addr);
+        fill_option!(similar_to, matching.similar_to);
+        fill_option!(
+            similarity,
+            matching
+                .similarity
+                .map(|ratio| matcher::parse_similarity(&ratio.to_string()))
+                .transpose()?
+        );
+        fill_flag!(cert_match, matching.cert_match);
+        fill_option!(cert_sha256, matching.cert_sha256);
+        fill_option!(spki_sha256, matching.spki_sha256);
+
+        // TLS
+        if !any_explicit(&["insecure", "ca_file", "retry_untrusted"]) {
+            fill_flag!(insecure, self.tls.insecure);
+            fill_option!(ca_file, self.tls.ca_file);
+            fill_flag!(retry_untrusted, self.tls.retry_untrusted);
+        }
+
+        // Output
+        let output = self.output;
+        fill_option!(output, output.file);
+        fill!(
+            format,
+            output
+                .format
+                .map(|f| parse_enum::<OutputFormat>(&f, "format"))
+                .transpose()?
+        );
+        fill!(checkpoint, output.checkpoint);
+        fill_flag!(verbose, output.verbose);
+
+        // Limits
+        let limits = self.limits;
+        fill_checked!(workers, limits.workers);
+        fill_flag!(no_adaptive, limits.adaptive.map(|adaptive| !adaptive));
+        if !any_explicit(&["threads", "current_thread"]) {
+            fill_checked!(threads, limits.threads);
+            fill_flag!(current_thread, limits.current_thread);
+        }
+        fill_option!(timeout, limits.timeout);
+        fill_option!(connect_timeout, limits.connect_timeout);
+        fill_option!(read_timeout, limits.read_timeout);
+        fill_option!(host_deadline, limits.host_deadline);
+        fill!(retries, limits.retries);
+        fill_checked!(rate, limits.rate);
+        fill_checked!(subnet_rate, limits.subnet_rate);
+        if !any_explicit(&["all", "max_finds", "stop_on_find"]) {
+            fill_flag!(all, limits.all);
+            fill_checked!(max_finds, limits.max_finds);
+        }
+
+        check_merged(matches, &from_file, &checked)
+    }
+
+    /// Describe the effective settings of a (merged) command line
+    ///
+    /// Limits left to auto-detection (workers, threads, timeout) are omitted,
+    /// so the profile stays portable across machines.
+    pub fn from_args(args: &Args) -> Self {
+        let flag = |set: bool| set.then_some(true);
+
+        Profile {
+            domain: args.domain.clone(),
+            targets: TargetSettings {
+                ranges: args.ranges.clone(),
+                ip_file: args.ip_file.clone(),
+                single_ip: args.single_ip.clone(),
+                open_from: args.open_from.clone(),
+                ports: Some(Spec::Text(args.ports.to_string())),
+                scheme: Some(
+                    if args.https {
+                        Scheme::Https
+                    } else {
+                        args.scheme
+                    }
+                    .to_string(),
+                ),
+                two_phase: flag(args.two_phase),
+                save_open: args.save_open.clone(),
+            },
+            request: RequestSettings {
+                method: Some(args.method.clone()),
+                paths: Some(args.paths.clone()),
+                headers: args.headers.clone(),
+                post_body: args.post_body.clone(),
+                max_body: Some(args.max_body),
+            },
+            matching: MatchSettings {
+                status: args.status_code.as_ref().map(|s| Spec::Text(s.to_string())),
+                content: args.content_match.clone(),
+                headers: args.header_match.clone(),
+                expr: args.match_expr.clone(),
+                baseline: flag(args.baseline),
+                baseline_addr: args.baseline_addr.clone(),
+                similar_to: args.similar_to.clone(),
+                similarity: args.similarity,
+                cert_match: flag(args.cert_match),
+                cert_sha256: args.cert_sha256.clone(),
+                spki_sha256: args.spki_sha256.clone(),
+            },
+            tls: TlsSettings {
+                insecure: flag(args.insecure),
+                ca_file: args.ca_file.clone(),
+                retry_untrusted: flag(args.retry_untrusted),
+            },
+            output: OutputSettings {
+                file: args.output.clone(),
+                format: args.output.as_ref().map(|_| args.format.to_string()),
+                checkpoint: Some(args.checkpoint.clone()),
+                verbose: flag(args.verbose),
+            },
+            limits: LimitSettings {
+                workers: args.workers,
+                adaptive: Some(!args.no_adaptive),
+                threads: args.threads,
+                current_thread: flag(args.current_thread),
+                timeout: args.timeout,
+                connect_timeout: args.connect_timeout,
+                read_timeout: args.read_timeout,
+                host_deadline: args.host_deadline,
+                retries: Some(args.retries),
+                rate: args.rate,
+                subnet_rate: args.subnet_rate,
+                all: flag(args.all),
+                max_finds: args.max_finds,
+            },
+        }
+    }
+
+    /// Render the profile as TOML
+    pub fn to_toml(&self) -> Result<String, Box<dyn std::error::Error>> {
+        Ok(toml::to_string_pretty(self)?)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use clap::FromArgMatches;
+
+    /// Load `toml` from a profile file and apply it over a bare command line
+    fn apply(name: &str, toml: &str) -> Result<Args, String> {
+        apply_over(name, &[], toml)
+    }
+
+    /// Load `toml` from a profile file and apply it over the command line `flags`
+    fn apply_over(name: &str, flags: &[&str], toml: &str) -> Result<Args, String> {
+        let path = std::env::temp_dir().join(format!(
+            "octointel-profile-{}-{}.toml",
+            std::process::id(),
+            name
+        ));
+        fs::write(&path, toml).unwrap();
+        let profile = Profile::load(&path);
+        fs::remove_file(&path).unwrap();
+
+        let argv = ["octointel", "example.com"].iter().chain(flags);
+        let matches = Args::command().get_matches_from(argv);
+        let mut args = Args::from_arg_matches(&matches).unwrap();
+        profile.unwrap().apply(&mut args, &matches)?;
+        Ok(args)
+    }
+
+    #[test]
+    fn zero_limits_are_rejected() {
+        for key in ["workers", "threads", "rate", "subnet_rate", "max_finds"] {
+            let result = apply(key, &format!("[limits]\n{} = 0\n", key));
+            let err = result
+                .err()
+                .unwrap_or_else(|| panic!("{} = 0 accepted", key));
+            let flag = format!("--{}", key.replace('_', "-"));
+            assert!(
+                err.starts_with(&format!("invalid value '0' for '{} <", flag)),
+                "{}",
+                err
+            );
+        }
+    }
+
+    #[test]
+    fn positive_limits_are_applied() {
+        let args = apply(
+            "positive",
+            "[limits]\nworkers = 8\nthreads = 2\nrate = 100\nsubnet_rate = 5\nmax_finds = 3\n",
+        )
+        .unwrap();
+        assert_eq!(args.workers, Some(8));
+        assert_eq!(args.threads, Some(2));
+        assert_eq!(args.rate, Some(100));
+        assert_eq!(args.subnet_rate, Some(5));
+        assert_eq!(args.max_finds, Some(3));
+    }
+
+    #[test]
+    fn conflicting_file_settings_are_rejected() {
+        for (name, toml) in [
+            ("tls", "[tls]\ninsecure = true\nca_file = \"ca.pem\"\n"),
+            ("threads", "[limits]\nthreads = 2\ncurrent_thread = true\n"),
+            (
+                "sources",
+                "[targets]\nranges = [\"10.0.0.0/24\"]\nip_file = \"ips.txt\"\n",
+            ),
+            (
+                "single-ip",
+                "[targets]\nsingle_ip = \"10.0.0.1\"\ntwo_phase = true\n",
+            ),
+            (
+                "open-from",
+                "[targets]\nopen_from = \"open.txt\"\ntwo_phase = true\n",
+            ),
+            (
+                "cert-mode",
+                "[match]\ncert_match = true\nsimilar_to = \"page.html\"\n",
+            ),
+        ] {
+            let err = apply(name, toml)
+                .err()
+                .unwrap_or_else(|| panic!("{} conflict accepted", name));
+            assert!(err.contains("cannot be used with"), "{}: {}", name, err);
+        }
+    }
+
+    #[test]
+    fn file_settings_conflict_with_flags_too() {
+        let err = apply_over(
+            "over-flag",
+            &["--single-ip", "10.0.0.1"],
+            "[targets]\ntwo_phase = true\n",
+        )
+        .unwrap_err();
+        assert!(err.contains("'--two-phase'"), "{}", err);
+        assert!(err.contains("'--single-ip <SINGLE_IP>'"), "{}", err);
+    }
+
+    #[test]
+    fn missing_requirements_are_rejected() {
+        let err = apply("save-open", "[targets]\nsave_open = \"open.txt\"\n").unwrap_err();
+        assert!(err.contains("--two-phase"), "{}", err);
+
+        let err = apply("format", "[output]\nformat = \"csv\"\n").unwrap_err();
+        assert!(err.contains("--output"), "{}", err);
+    }
+
+    #[test]
+    fn disabled_flags_in_the_file_do_not_conflict() {
+        let args = apply(
+            "disabled",
+            "[tls]\ninsecure = false\nca_file = \"ca.pem\"\n\n[limits]\nthreads = 2\ncurrent_thread = false\n",
+        )
+        .unwrap();
+        assert!(!args.insecure);
+        assert_eq!(args.threads, Some(2));
+    }
+}
@@ -0,0 +1,173 @@
+use ipnetwork::{IpNetwork, NetworkSize};
+use std::sync::atomic::{AtomicU64, AtomicUsize, Ordering};
+
+/// Smallest IPv6 prefix accepted for scanning (/112 = 65,536 addresses)
+///
+/// IPv6 networks are far too large to sweep, so v6 targets must be explicit
+/// addresses or small prefixes (e.g. a known /120 of a hosting provider).
+pub const MIN_IPV6_PREFIX: u8 = 112;
+
+/// Parse an IPv4/IPv6 range in CIDR notation or a single address
+///
+/// # Returns
+/// * `Ok(IpNetwork)` - Parsed network (single addresses become /32 or /128)
+/// * `Err` - If the range is malformed or an IPv6 prefix is too large to sweep
+pub fn parse_ip_range(range: &str) -> Result<IpNetwork, String> {
+    let network: IpNetwork = range.trim().parse().map_err(|e| format!("{}", e))?;
+    check_range(&network)?;
+    Ok(network)
+}
+
+/// Check that a range is small enough to sweep
+///
+/// # Returns
+/// * `Err` - If the range is an IPv6 prefix larger than `MIN_IPV6_PREFIX` allows
+pub fn check_range(network: &IpNetwork) -> Result<(), String> {
+    if let IpNetwork::V6(v6) = network {
+        if v6.prefix() < MIN_IPV6_PREFIX {
+            return Err(format!(
+                "IPv6 range /{} is too large to scan; use explicit addresses or a /{} or smaller prefix",
+                v6.prefix(),
+                MIN_IPV6_PREFIX
+            ));
+        }
+    }
+    Ok(())
+}
+
+/// Number of addresses in a range
+///
+/// Saturates at `u64::MAX` for IPv6 ranges too large to count (which
+/// [`check_range`] rejects anyway), so a range never counts as empty.
+pub fn range_size(network: &IpNetwork) -> u64 {
+    match network.size() {
+        NetworkSize::V4(n) => n as u64,
+        NetworkSize::V6(n) => u64::try_from(n).unwrap_or(u64::MAX),
+    }
+}
+
+/// Progress of one range within a multi-range scan
+#[derive(Debug)]
+pub struct RangeCounter {
+    pub network: IpNetwork,
+    /// Targets in the range (every address on every port)
+    pub total: u64,
+    /// Targets fully scanned, including those skipped on resume
+    pub done: AtomicU64,
+    /// Hits (or open endpoints, during discovery) found in the range
+    pub found: AtomicU64,
+}
+
+impl RangeCounter {
+    /// Whether every target of the range has been scanned
+    pub fn is_finished(&self) -> bool {
+        self.done.load(Ordering::Relaxed) >= self.total
+    }
+}
+
+/// Per-range counters for a scan that runs all ranges in one pipeline
+///
+/// Targets from different ranges are in flight at the same time, so
+/// completion is tracked per range rather than by a position in the list.
+#[derive(Debug)]
+pub struct RangeTracker {
+    ranges: Vec<RangeCounter>,
+    finished: AtomicUsize,
+}
+
+impl RangeTracker {
+    /// Create counters for `networks`, treating the first `skip` targets as done
+    ///
+    /// # Arguments
+    /// * `networks` - Ranges in scan order
+    /// * `sizes` - Number of targets in each range
+    /// * `skip` - Targets (across all ranges, in order) already scanned when resuming
+    pub fn new(networks: &[IpNetwork], sizes: impl Fn(&IpNetwork) -> u64, skip: u64) -> Self {
+        let mut remaining = skip;
+        let ranges: Vec<RangeCounter> = networks
+            .iter()
+            .map(|network| {
+                let total = sizes(network);
+                let done = remaining.min(total);
+                remaining -= done;
+                RangeCounter {
+                    network: *network,
+                    total,
+                    done: AtomicU64::new(done),
+                    found: AtomicU64::new(0),
+                }
+            })
+            .collect();
+        let finished = ranges.iter().filter(|r| r.is_finished()).count();
+
+        Self {
+            ranges,
+            finished: AtomicUsize::new(finished),
+        }
+    }
+
+    /// Count one scanned target of range `index`
+    ///
+    /// # Returns
+    /// * `Some(range)` - The target was the range's last one
+    /// * `None` - The range still has targets left
+    pub fn complete(&self, index: usize) -> Option<&RangeCounter> {
+        let range = &self.ranges[index];
+        let done = range.done.fetch_add(1, Ordering::Relaxed) + 1;
+        if done == range.total {
+            self.finished.fetch_add(1, Ordering::Relaxed);
+            Some(range)
+        } else {
+            None
+        }
+    }
+
+    /// Count a hit (or open endpoint) in range `index`
+    pub fn found(&self, index: usize) {
+        self.ranges[index].found.fetch_add(1, Ordering::Relaxed);
+    }
+
+    /// Count a hit carried over from a checkpoint in the first range containing its address
+    pub fn found_ip(&self, ip: std::net::IpAddr) {
+        if let Some(range) = self.ranges.iter().find(|r| r.network.contains(ip)) {
+            range.found.fetch_add(1, Ordering::Relaxed);
+        }
+    }
+
+    /// All ranges, in scan order
+    pub fn ranges(&self) -> &[RangeCounter] {
+        &self.ranges
+    }
+
+    /// Number of ranges whose targets have all been scanned
+    pub fn finished(&self) -> usize {
+        self.finished.load(Ordering::Relaxed)
+    }
+
+    /// Short status for the progress line, e.g. "ranges 3/500"
+    pub fn status(&self) -> String {
+        format!("ranges {}/{}", self.finished(), self.ranges.len())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn large_ipv6_ranges_are_rejected() {
+        assert!(parse_ip_range("2001:db8::/112").is_ok());
+        assert!(parse_ip_range("2001:db8::/100").is_err());
+        assert!(parse_ip_range("2001:db8::/64").is_err());
+        assert!(parse_ip_range("10.0.0.0/8").is_ok());
+    }
+
+    #[test]
+    fn range_size_never_truncates_to_zero() {
+        let size = |range: &str| range_size(&range.parse().unwrap());
+        assert_eq!(size("10.0.0.0/24"), 256);
+        assert_eq!(size("2001:db8::/120"), 256);
+        assert_eq!(size("2001:db8::/64"), u64::MAX);
+        assert_eq!(size("2001:db8::/48"), u64::MAX);
+    }
+}
@@ -0,0 +1,138 @@
+use ipnetwork::IpNetwork;
+use std::collections::HashMap;
+use std::net::{IpAddr, Ipv4Addr, Ipv6Addr};
+use std::sync::Mutex;
+use std::time::Duration;
+use tokio::time::Instant;
+
+/// Number of tracked subnets above which idle subnet buckets are dropped
+const SUBNET_PRUNE_THRESHOLD: usize = 4096;
+
+/// Token bucket that hands out send slots at a fixed rate
+///
+/// Tokens may go negative: every caller reserves the next free slot and is
+/// told how long to wait for it, so waiters are served in order.
+#[derive(Debug)]
+struct TokenBucket {
+    rate: f64,
+    capacity: f64,
+    tokens: f64,
+    updated: Instant,
+}
+
+impl TokenBucket {
+    fn new(rate: u32, capacity: f64) -> Self {
+        Self {
+            rate: rate as f64,
+            capacity,
+            tokens: capacity,
+            updated: Instant::now(),
+        }
+    }
+
+    /// Refill for the time elapsed since the last update
+    fn refill(&mut self, now: Instant) {
+        let elapsed = now.saturating_duration_since(self.updated).as_secs_f64();
+        self.tokens = (self.tokens + elapsed * self.rate).min(self.capacity);
+        self.updated = now;
+    }
+
+    /// Take one token and return how long the caller must wait before using it
+    fn reserve(&mut self, now: Instant) -> Duration {
+        self.refill(now);
+        self.tokens -= 1.0;
+        if self.tokens >= 0.0 {
+            Duration::ZERO
+        } else {
+            Duration::from_secs_f64(-self.tokens / self.rate)
+        }
+    }
+
+    /// Whether the bucket has fully refilled (indistinguishable from a new one)
+    fn is_idle(&self, now: Instant) -> bool {
+        let elapsed = now.saturating_duration_since(self.updated).as_secs_f64();
+        self.tokens + elapsed * self.rate >= self.capacity
+    }
+}
+
+/// Connection rate limiter shared by every scan task
+///
+/// Combines an optional global connections-per-second limit with an
+/// optional per-subnet limit, so no single /24 (IPv6: /64) receives more
+/// than a set number of connections per second.
+#[derive(Debug)]
+pub struct RateLimiter {
+    global: Option<Mutex<TokenBucket>>,
+    subnet_rate: Option<u32>,
+    subnets: Mutex<HashMap<IpAddr, TokenBucket>>,
+}
+
+impl RateLimiter {
+    /// Create a rate limiter
+    ///
+    /// # Arguments
+    /// * `rate` - Maximum connections per second across all targets
+    /// * `subnet_rate` - Maximum connections per second to any single /24 (IPv6: /64)
+    ///
+    /// # Returns
+    /// * `None` - If neither limit is set
+    pub fn new(rate: Option<u32>, subnet_rate: Option<u32>) -> Option<Self> {
+        if rate.is_none() && subnet_rate.is_none() {
+            return None;
+        }
+
+        Some(Self {
+            // Allow ~10ms worth of burst, since timers cannot space sends any finer
+            global: rate.map(|r| Mutex::new(TokenBucket::new(r, (r as f64 / 100.0).max(1.0)))),
+            subnet_rate,
+            subnets: Mutex::new(HashMap::new()),
+        })
+    }
+
+    /// Wait until a connection to `ip` is allowed by every configured limit
+    pub async fn acquire(&self, ip: IpAddr) {
+        // Subnet first, so the global slot is only claimed when we are about to send
+        if let Some(rate) = self.subnet_rate {
+            let wait = {
+                let now = Instant::now();
+                let mut subnets = self.subnets.lock().unwrap();
+                if subnets.len() > SUBNET_PRUNE_THRESHOLD {
+                    subnets.retain(|_, bucket| !bucket.is_idle(now));
+                }
+                subnets
+                    .entry(subnet_key(ip))
+                    .or_insert_with(|| TokenBucket::new(rate, 1.0))
+                    .reserve(now)
+            };
+            if !wait.is_zero() {
+                tokio::time::sleep(wait).await;
+            }
+        }
+
+        if let Some(ref global) = self.global {
+            let wait = global.lock().unwrap().reserve(Instant::now());
+            if !wait.is_zero() {
+                tokio::time::sleep(wait).await;
+            }
+        }
+    }
+}
+
+/// Network address of the /24 (IPv4) or /64 (IPv6) containing `ip`
+fn subnet_key(ip: IpAddr) -> IpAddr {
+    match ip {
+        IpAddr::V4(v4) => IpAddr::V4(Ipv4Addr::from(u32::from(v4) & 0xFFFF_FF00)),
+        IpAddr::V6(v6) => IpAddr::V6(Ipv6Addr::from(u128::from(v6) & !((1u128 << 64) - 1))),
+    }
+}
+
+/// Number of /24 (IPv4) or /64 (IPv6) subnets a range spans
+///
+/// Ranges smaller than one subnet count as one.
+pub fn subnet_count(network: &IpNetwork) -> u64 {
+    let spare_bits = match network {
+        IpNetwork::V4(v4) => 24u8.saturating_sub(v4.prefix()),
+        IpNetwork::V6(v6) => 64u8.saturating_sub(v6.prefix()),
+    };
+    1u64.checked_shl(u32::from(spare_bits)).unwrap_or(u64::MAX)
+}
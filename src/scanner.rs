@@ -0,0 +1,2148 @@
+use crate::adaptive::{AdaptiveLimiter, ConnectOutcome, ADJUST_INTERVAL};
+use crate::conn::{MaybeTlsStream, Scheme};
+use crate::event::{Phase, ScanEvent, ScanSummary, Targets};
+use crate::http::{self, HttpResponse, StatusSet};
+use crate::matcher::{
+    self, AndMatcher, BodyMatcher, HeaderMatcher, MatchInput, Matcher, StatusMatcher,
+};
+use crate::ports::PortList;
+use crate::ranges::{range_size, RangeTracker};
+use crate::rate::{self, RateLimiter};
+use crate::stats::ScanStats;
+use crate::tls::{self, CertMatcher, CertVerification};
+use futures::stream::{self, Stream, StreamExt};
+use ipnetwork::IpNetwork;
+use regex::Regex;
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use std::future::Future;
+use std::net::{IpAddr, Ipv4Addr, Ipv6Addr, SocketAddr};
+use std::sync::atomic::{AtomicBool, AtomicU64, Ordering};
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+use tokio::io::AsyncWriteExt;
+use tokio::net::TcpStream;
+use tokio::sync::mpsc;
+use tokio::task::JoinSet;
+use tokio::time::timeout;
+use tokio_rustls::client::TlsStream;
+use tokio_rustls::rustls::pki_types::ServerName;
+use tokio_rustls::TlsConnector;
+use tokio_util::sync::CancellationToken;
+
+/// Configuration for creating a new Scanner instance
+///
+/// Build one with [`ScannerConfig::builder`], which starts from the command
+/// line's defaults (except the auto-detected workers and timeouts).
+#[derive(Debug, Clone)]
+pub struct ScannerConfig {
+    pub domain: String,
+    pub connect_timeout: Duration,
+    pub read_timeout: Duration,
+    pub host_deadline: Option<Duration>,
+    pub retries: u32,
+    pub workers: usize,
+    pub adaptive: bool,
+    pub rate: Option<u32>,
+    pub subnet_rate: Option<u32>,
+    pub max_finds: Option<u64>,
+    pub method: String,
+    pub paths: Vec<String>,
+    /// Status codes a response must have (`None`: any status)
+    pub status_codes: Option<StatusSet>,
+    pub content_match: Option<String>,
+    pub header_match: Option<Vec<String>>,
+    /// Boolean match expression (see [`matcher::parse`]), combined with the other rules
+    pub match_expr: Option<String>,
+    /// Custom matchers, combined with the other rules
+    pub matchers: Vec<Arc<dyn Matcher>>,
+    pub max_body: usize,
+    pub headers: Option<Vec<String>>,
+    pub post_body: Option<String>,
+    /// Ports probed on every address. They also decide whether a TLS
+    /// connector is prepared, so endpoint scans should list their ports here.
+    pub ports: PortList,
+    pub scheme: Scheme,
+    pub cert_verification: CertVerification,
+    /// Probe hosts failing system root verification again without it; their
+    /// hits carry `cert_trusted: Some(false)`. Custom roots are never bypassed.
+    pub retry_untrusted: bool,
+    pub cert_match: bool,
+    pub cert_sha256: Option<String>,
+    pub spki_sha256: Option<String>,
+    /// Find open endpoints with a TCP-connect-only pass before probing (range scans only)
+    pub two_phase: bool,
+    /// Report per-target failures and diagnostics as events
+    pub verbose: bool,
+}
+
+impl ScannerConfig {
+    /// Start a configuration for `domain` with the command line's defaults
+    ///
+    /// Defaults: 1000ms connect and read timeouts, 1000 adaptive workers
+    /// (the command line picks both from the machine's CPUs and memory),
+    /// `HEAD /` on port 80 (scheme auto-detected on other ports), status 202,
+    /// stop after the first match. Like on the command line, the status
+    /// default is dropped once a match expression or custom matcher brings
+    /// its own rules, unless [`ScannerConfigBuilder::status_codes`] is set.
+    pub fn builder(domain: impl Into<String>) -> ScannerConfigBuilder {
+        ScannerConfigBuilder {
+            status_codes_set: false,
+            config: ScannerConfig {
+                domain: domain.into(),
+                connect_timeout: Duration::from_millis(1000),
+                read_timeout: Duration::from_millis(1000),
+                host_deadline: None,
+                retries: 0,
+                workers: 1000,
+                adaptive: true,
+                rate: None,
+                subnet_rate: None,
+                max_finds: Some(1),
+                method: "HEAD".to_string(),
+                paths: vec!["/".to_string()],
+                status_codes: Some("202".parse().expect("valid default status")),
+                content_match: None,
+                header_match: None,
+                match_expr: None,
+                matchers: Vec::new(),
+                max_body: 256 * 1024,
+                headers: None,
+                post_body: None,
+                ports: "80".parse().expect("valid default port"),
+                scheme: Scheme::Auto,
+                cert_verification: CertVerification::SystemRoots,
+                retry_untrusted: false,
+                cert_match: false,
+                cert_sha256: None,
+                spki_sha256: None,
+                two_phase: false,
+                verbose: false,
+            },
+        }
+    }
+
+    /// Stable hash of every setting that affects which hosts match, or the
+    /// order range targets are scanned in (a subnet rate interleaves them)
+    ///
+    /// Custom matchers are included by their description, so rules derived
+    /// from a live page change it whenever the page does; the command line
+    /// leaves those out and fingerprints the flags they came from instead.
+    /// Stored in checkpoints so a resumed scan cannot silently continue
+    /// with a different target or configuration, or skip targets of a
+    /// different order.
+    pub fn fingerprint(&self) -> String {
+        let canonical = format!(
+            "domain={}|ports={}|scheme={}|method={}|paths={:?}|status={:?}|content={:?}|header_match={:?}|match={:?}|matchers={:?}|headers={:?}|post_body={:?}|cert_match={}|cert_sha256={:?}|spki_sha256={:?}|interleave={}",
+            self.domain,
+            self.ports,
+            self.scheme,
+            self.method,
+            self.paths,
+            self.status_codes.as_ref().map(ToString::to_string),
+            self.content_match,
+            self.header_match,
+            self.match_expr,
+            self.matchers.iter().map(|m| m.describe()).collect::<Vec<_>>(),
+            self.headers,
+            self.post_body,
+            self.cert_match,
+            self.cert_sha256,
+            self.spki_sha256,
+            self.subnet_rate.is_some(),
+        );
+        Sha256::digest(canonical.as_bytes())
+            .iter()
+            .map(|b| format!("{:02x}", b))
+            .collect()
+    }
+}
+
+/// Builder for [`ScannerConfig`], created by [`ScannerConfig::builder`]
+///
+/// Every setter replaces the default; validation (paths, headers, regexes,
+/// TLS roots) happens in [`Scanner::new`].
+#[derive(Debug, Clone)]
+pub struct ScannerConfigBuilder {
+    config: ScannerConfig,
+    /// Whether the status codes were set, rather than left at the default
+    status_codes_set: bool,
+}
+
+impl ScannerConfigBuilder {
+    /// TCP connect timeout
+    pub fn connect_timeout(mut self, timeout: Duration) -> Self {
+        self.config.connect_timeout = timeout;
+        self
+    }
+
+    /// TLS handshake and HTTP response timeout
+    pub fn read_timeout(mut self, timeout: Duration) -> Self {
+        self.config.read_timeout = timeout;
+        self
+    }
+
+    /// Total time allowed per host across all paths and retries
+    pub fn host_deadline(mut self, deadline: Option<Duration>) -> Self {
+        self.config.host_deadline = deadline;
+        self
+    }
+
+    /// Retries per probe after a transient error
+    pub fn retries(mut self, retries: u32) -> Self {
+        self.config.retries = retries;
+        self
+    }
+
+    /// Maximum concurrent targets
+    pub fn workers(mut self, workers: usize) -> Self {
+        self.config.workers = workers;
+        self
+    }
+
+    /// Adapt concurrency (up to `workers`) to observed timeouts and errors
+    pub fn adaptive(mut self, adaptive: bool) -> Self {
+        self.config.adaptive = adaptive;
+        self
+    }
+
+    /// Maximum new connections per second across the scan
+    pub fn rate(mut self, rate: Option<u32>) -> Self {
+        self.config.rate = rate;
+        self
+    }
+
+    /// Maximum new connections per second to any single /24 (IPv6: /64)
+    pub fn subnet_rate(mut self, rate: Option<u32>) -> Self {
+        self.config.subnet_rate = rate;
+        self
+    }
+
+    /// Stop after this many hits (`None` scans exhaustively, `Some(0)` scans nothing)
+    pub fn max_finds(mut self, max_finds: Option<u64>) -> Self {
+        self.config.max_finds = max_finds;
+        self
+    }
+
+    /// HTTP method: HEAD, GET or POST
+    pub fn method(mut self, method: impl Into<String>) -> Self {
+        self.config.method = method.into();
+        self
+    }
+
+    /// Request paths, tried in order until one matches
+    pub fn paths(mut self, paths: Vec<String>) -> Self {
+        self.config.paths = paths;
+        self
+    }
+
+    /// Status codes a response must have (`None`: any status)
+    pub fn status_codes(mut self, status_codes: Option<StatusSet>) -> Self {
+        self.config.status_codes = status_codes;
+        self.status_codes_set = true;
+        self
+    }
+
+    /// Regex the response head or body must match
+    pub fn content_match(mut self, pattern: Option<String>) -> Self {
+        self.config.content_match = pattern;
+        self
+    }
+
+    /// Add a response header rule ("Name: regex")
+    pub fn header_match(mut self, rule: impl Into<String>) -> Self {
+        self.config
+            .header_match
+            .get_or_insert_with(Vec::new)
+            .push(rule.into());
+        self
+    }
+
+    /// Boolean match expression, e.g. `title~Example and not header:Server~cloudflare`
+    pub fn match_expr(mut self, expr: Option<String>) -> Self {
+        self.config.match_expr = expr;
+        self
+    }
+
+    /// Add a custom matcher
+    pub fn matcher(mut self, matcher: impl Matcher + 'static) -> Self {
+        self.config.matchers.push(Arc::new(matcher));
+        self
+    }
+
+    /// Maximum response body bytes read for content matching
+    pub fn max_body(mut self, max_body: usize) -> Self {
+        self.config.max_body = max_body;
+        self
+    }
+
+    /// Add a request header ("Name: Value")
+    pub fn header(mut self, header: impl Into<String>) -> Self {
+        self.config
+            .headers
+            .get_or_insert_with(Vec::new)
+            .push(header.into());
+        self
+    }
+
+    /// Body sent with POST requests
+    pub fn post_body(mut self, body: Option<String>) -> Self {
+        self.config.post_body = body;
+        self
+    }
+
+    /// Ports probed on every address
+    pub fn ports(mut self, ports: PortList) -> Self {
+        self.config.ports = ports;
+        self
+    }
+
+    /// Protocol spoken on the ports
+    pub fn scheme(mut self, scheme: Scheme) -> Self {
+        self.config.scheme = scheme;
+        self
+    }
+
+    /// How certificates of HTTPS ports are validated
+    pub fn cert_verification(mut self, verification: CertVerification) -> Self {
+        self.config.cert_verification = verification;
+        self
+    }
+
+    /// Retry hosts failing system root verification without it, flagging their hits
+    pub fn retry_untrusted(mut self, retry: bool) -> Self {
+        self.config.retry_untrusted = retry;
+        self
+    }
+
+    /// Match by certificate names covering the domain instead of HTTP
+    pub fn cert_match(mut self, cert_match: bool) -> Self {
+        self.config.cert_match = cert_match;
+        self
+    }
+
+    /// Match by certificate SHA-256 fingerprint
+    pub fn cert_sha256(mut self, sha256: Option<String>) -> Self {
+        self.config.cert_sha256 = sha256;
+        self
+    }
+
+    /// Match by SHA-256 of the certificate's SubjectPublicKeyInfo
+    pub fn spki_sha256(mut self, sha256: Option<String>) -> Self {
+        self.config.spki_sha256 = sha256;
+        self
+    }
+
+    /// Find open endpoints before probing (range scans only)
+    pub fn two_phase(mut self, two_phase: bool) -> Self {
+        self.config.two_phase = two_phase;
+        self
+    }
+
+    /// Report per-target failures and diagnostics as events
+    pub fn verbose(mut self, verbose: bool) -> Self {
+        self.config.verbose = verbose;
+        self
+    }
+
+    /// Finish the configuration
+    ///
+    /// Match expressions and custom matchers bring their own rules, so the
+    /// default status 202 only applies without them (as on the command line).
+    pub fn build(mut self) -> ScannerConfig {
+        let own_rules = self.config.match_expr.is_some() || !self.config.matchers.is_empty();
+        if own_rules && !self.status_codes_set {
+            self.config.status_codes = None;
+        }
+        self.config
+    }
+}
+
+/// Connect attempts per target when failing on local fd/port exhaustion
+const RESOURCE_RETRIES: u32 = 5;
+
+/// Backoff before the first retry of a transient failure (doubles per retry)
+const RETRY_BACKOFF_BASE: Duration = Duration::from_millis(100);
+
+/// Upper bound on the backoff between retries
+const RETRY_BACKOFF_MAX: Duration = Duration::from_secs(2);
+
+/// Time between two `Progress` events of a running phase
+const PROGRESS_INTERVAL: Duration = Duration::from_millis(200);
+
+/// One request sent to a host: a bare TCP connect, a TLS certificate check or an HTTP path
+enum Probe<'a> {
+    TcpOpen,
+    Certificate(&'a CertMatcher),
+    Http { path: &'a str, request: &'a [u8] },
+}
+
+/// Why a probe produced no response to match
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum ProbeFailure {
+    /// TCP connect or TLS handshake failed
+    Connect { transient: bool },
+    /// Connected, but sending the request or reading the response failed
+    Exchange { transient: bool },
+    /// The per-host deadline passed
+    DeadlineExceeded,
+}
+
+impl ProbeFailure {
+    /// Whether retrying the probe might succeed
+    fn is_transient(self) -> bool {
+        match self {
+            ProbeFailure::Connect { transient } | ProbeFailure::Exchange { transient } => transient,
+            ProbeFailure::DeadlineExceeded => false,
+        }
+    }
+}
+
+/// State shared by every probe of one host (ip:port)
+#[derive(Default)]
+struct HostState {
+    /// Host deadline, started with the first connection attempt
+    deadline: Option<Instant>,
+    /// Whether the port speaks TLS, once known (configured or detected)
+    tls: Option<bool>,
+    /// The certificate failed verification; later handshakes skip it
+    untrusted: bool,
+}
+
+/// Errors worth retrying: the host may well answer on a second try
+fn is_transient(e: &std::io::Error) -> bool {
+    use std::io::ErrorKind;
+    matches!(
+        e.kind(),
+        ErrorKind::ConnectionReset
+            | ErrorKind::ConnectionAborted
+            | ErrorKind::BrokenPipe
+            | ErrorKind::TimedOut
+    )
+}
+
+/// Whether a failed TLS handshake shows the peer doesn't speak TLS at all
+///
+/// A plaintext HTTP server answers a ClientHello with an HTTP error (not a
+/// valid TLS record) or just closes the connection.
+fn is_plaintext_reply(e: &std::io::Error) -> bool {
+    use tokio_rustls::rustls;
+    match e.kind() {
+        std::io::ErrorKind::UnexpectedEof => true,
+        std::io::ErrorKind::InvalidData => e
+            .get_ref()
+            .and_then(|inner| inner.downcast_ref::<rustls::Error>())
+            .is_some_and(|tls_error| matches!(tls_error, rustls::Error::InvalidMessage(_))),
+        _ => false,
+    }
+}
+
+/// Whether a failed TLS handshake was rejected over the server's certificate
+fn is_certificate_error(e: &std::io::Error) -> bool {
+    use tokio_rustls::rustls;
+    e.get_ref()
+        .and_then(|inner| inner.downcast_ref::<rustls::Error>())
+        .is_some_and(|tls_error| {
+            matches!(
+                tls_error,
+                rustls::Error::InvalidCertificate(_) | rustls::Error::NoCertificatesPresented
+            )
+        })
+}
+
+/// Shorten `timeout` so it ends no later than the host deadline
+fn cap_to_deadline(timeout: Duration, deadline: Option<Instant>) -> Duration {
+    match deadline {
+        Some(deadline) => timeout.min(deadline.saturating_duration_since(Instant::now())),
+        None => timeout,
+    }
+}
+
+/// `DeadlineExceeded` if the host deadline has passed
+fn past_deadline(deadline: Option<Instant>) -> Option<ProbeFailure> {
+    deadline
+        .is_some_and(|d| Instant::now() >= d)
+        .then_some(ProbeFailure::DeadlineExceeded)
+}
+
+/// Jittered exponential backoff before retry number `attempt` (1-based)
+///
+/// Waits between half and all of `RETRY_BACKOFF_BASE * 2^(attempt-1)`, so
+/// retries of hosts that failed together don't all fire at the same moment.
+fn retry_backoff(attempt: u32) -> Duration {
+    let full = RETRY_BACKOFF_BASE
+        .saturating_mul(1 << (attempt - 1).min(16))
+        .min(RETRY_BACKOFF_MAX);
+    let half = full.as_millis() as u64 / 2;
+    Duration::from_millis(half + fastrand::u64(0..=half))
+}
+
+/// Number of body characters kept in a hit's excerpt
+const EXCERPT_LEN: usize = 200;
+
+/// Body bytes read for the excerpt when no matcher needs the body
+const EXCERPT_BODY: usize = 4 * 1024;
+
+/// A backend that matched every configured rule
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ScanHit {
+    /// Address that answered
+    pub ip: IpAddr,
+    /// Port that was probed
+    pub port: u16,
+    /// "http" or "https"
+    pub scheme: String,
+    /// Request path that matched (absent in certificate mode)
+    pub path: Option<String>,
+    /// Observed HTTP status (absent in certificate mode)
+    pub status: Option<u16>,
+    /// Names of the rules that matched
+    pub matched: Vec<String>,
+    /// Time from starting the connection to a complete response, in milliseconds
+    pub response_time_ms: u64,
+    /// Start of the response body with whitespace collapsed
+    pub excerpt: String,
+    /// Whether the certificate passed verification; `Some(false)` when it
+    /// didn't and the hit came from a retry without verification (`None`,
+    /// written as `null`, when no retry was possible)
+    #[serde(default)]
+    pub cert_trusted: Option<bool>,
+}
+
+impl ScanHit {
+    /// Address and port that answered
+    pub fn endpoint(&self) -> SocketAddr {
+        SocketAddr::new(self.ip, self.port)
+    }
+
+    /// One-line description used in terminal output
+    pub fn summary(&self) -> String {
+        let summary = match (&self.path, self.status) {
+            (Some(path), Some(status)) => format!(
+                "Path: {}, Status: {}, Matched: {}",
+                path,
+                status,
+                self.matched.join(", ")
+            ),
+            _ => format!("Matched: {}", self.matched.join(", ")),
+        };
+        if self.cert_trusted == Some(false) {
+            format!("{} (untrusted certificate)", summary)
+        } else {
+            summary
+        }
+    }
+}
+
+/// Scanner configuration and state management
+///
+/// Holds all configuration needed for scanning IP ranges, including:
+/// - Target domain and connection parameters
+/// - HTTP request configuration (method, headers, body)
+/// - Content and response header matching rules (regex patterns)
+/// - Concurrency and performance settings
+/// - Ports and the scheme spoken on each of them
+/// - TLS connector and SNI name when any port may speak HTTPS
+/// - Certificate matching rules when identifying origins by TLS certificate
+///
+/// A scanner runs one scan: create it with [`Scanner::new`] and consume it
+/// with [`Scanner::scan`], which reports progress and hits as [`ScanEvent`]s.
+#[derive(Clone)]
+pub struct Scanner {
+    connect_timeout: Duration,
+    read_timeout: Duration,
+    host_deadline: Option<Duration>,
+    retries: u32,
+    stats: Arc<ScanStats>,
+    workers: usize,
+    concurrency: Option<Arc<AdaptiveLimiter>>,
+    stop_flag: Arc<AtomicBool>,
+    interrupted: Arc<AtomicBool>,
+    found_count: Arc<AtomicU64>,
+    max_finds: Option<u64>,
+    requests: Arc<Vec<(String, Vec<u8>)>>,
+    method: Arc<String>,
+    matcher: Arc<AndMatcher>,
+    max_body: usize,
+    ports: Arc<PortList>,
+    scheme: Scheme,
+    rate_limiter: Option<Arc<RateLimiter>>,
+    /// Spread range targets across subnets (set with a per-subnet limit)
+    interleave_subnets: bool,
+    tls: Option<(TlsConnector, ServerName<'static>)>,
+    /// Connector without verification, for hosts whose certificate fails it
+    unverified_tls: Option<TlsConnector>,
+    cert_matcher: Option<Arc<CertMatcher>>,
+    two_phase: bool,
+    events: Option<mpsc::UnboundedSender<ScanEvent>>,
+    verbose: bool,
+}
+
+impl Scanner {
+    /// Create a new Scanner instance with all configuration
+    ///
+    /// # Arguments
+    /// * `config` - Scanner configuration struct
+    ///
+    /// # Returns
+    /// * `Ok(Scanner)` - Configured scanner ready to use
+    /// * `Err` - If configuration is invalid (bad regex, invalid method or path, unusable CA roots, etc.)
+    pub fn new(config: ScannerConfig) -> Result<Self, Box<dyn std::error::Error>> {
+        // Validate request paths before they end up in a request line
+        if config.paths.is_empty() {
+            return Err("At least one request path is required".into());
+        }
+        for path in &config.paths {
+            if !path.starts_with('/') || path.chars().any(|c| c.is_whitespace() || c.is_control()) {
+                return Err(format!(
+                    "Invalid request path: '{}'. Expected an absolute path like '/health'",
+                    path
+                )
+                .into());
+            }
+        }
+
+        // Build HTTP request headers with specified method (request line is added per path)
+        let mut request = match config.method.as_str() {
+            "HEAD" | "GET" => format!("Host: {}\r\n", config.domain),
+            "POST" => {
+                let body = config.post_body.as_deref().unwrap_or("");
+                format!(
+                    "Host: {}\r\nContent-Length: {}\r\n",
+                    config.domain,
+                    body.len()
+                )
+            }
+            _ => return Err(format!("Unsupported HTTP method: {}", config.method).into()),
+        };
+
+        // Add custom headers if provided
+        if let Some(ref custom_headers) = config.headers {
+            for header in custom_headers {
+                if !header.contains(':') {
+                    return Err(format!(
+                        "Invalid header format: '{}'. Expected 'Header: Value'",
+                        header
+                    )
+                    .into());
+                }
+                request.push_str(header);
+                request.push_str("\r\n");
+            }
+        }
+
+        // Add standard headers
+        request.push_str("Connection: close\r\n");
+        request.push_str("User-Agent: octointel/2.0\r\n");
+
+        // Complete headers and add body for POST
+        request.push_str("\r\n");
+        if config.method == "POST" {
+            if let Some(body) = config.post_body {
+                request.push_str(&body);
+            }
+        }
+
+        // One request per path, sharing everything after the request line
+        let requests = config
+            .paths
+            .iter()
+            .map(|path| {
+                let line = format!("{} {} HTTP/1.1\r\n", config.method, path);
+                (path.clone(), [line.as_bytes(), request.as_bytes()].concat())
+            })
+            .collect();
+
+        // Every rule must match: status, content, header rules, the match expression and custom matchers
+        let mut rules: Vec<Box<dyn Matcher>> = Vec::new();
+        if let Some(status_codes) = config.status_codes {
+            rules.push(Box::new(StatusMatcher(status_codes)));
+        }
+
+        // Compile regex if content matching is enabled
+        if let Some(pattern) = config.content_match {
+            match Regex::new(&pattern) {
+                Ok(re) => rules.push(Box::new(BodyMatcher::content(re))),
+                Err(e) => return Err(format!("Invalid regex pattern: {}", e).into()),
+            }
+        }
+
+        // Compile header matchers ("Name: regex" - every one must match some header of that name)
+        if let Some(ref header_matches) = config.header_match {
+            for rule in header_matches {
+                let Some((name, pattern)) = rule.split_once(':') else {
+                    return Err(format!(
+                        "Invalid header match format: '{}'. Expected 'Header: regex'",
+                        rule
+                    )
+                    .into());
+                };
+                let name = name.trim();
+                if name.is_empty() {
+                    return Err(format!("Missing header name in header match: '{}'", rule).into());
+                }
+                match Regex::new(pattern.trim_start()) {
+                    Ok(re) => rules.push(Box::new(HeaderMatcher::new(name, re))),
+                    Err(e) => {
+                        return Err(
+                            format!("Invalid regex pattern for header {}: {}", name, e).into()
+                        )
+                    }
+                }
+            }
+        }
+
+        if let Some(ref expr) = config.match_expr {
+            let matcher = matcher::parse(expr)
+                .map_err(|e| format!("Invalid match expression '{}': {}", expr, e))?;
+            rules.push(matcher);
+        }
+        for matcher in config.matchers {
+            rules.push(Box::new(matcher));
+        }
+        let matcher = AndMatcher(rules);
+
+        // Buffer the full body only when a matcher looks at it; otherwise
+        // just enough for the hit's excerpt (HEAD responses have none)
+        let max_body = if matcher.needs_body() {
+            config.max_body
+        } else if config.method != "HEAD" {
+            EXCERPT_BODY.min(config.max_body)
+        } else {
+            0 // Status line and headers only
+        };
+
+        // Certificate matching replaces the HTTP exchange entirely
+        let cert_matcher =
+            if config.cert_match || config.cert_sha256.is_some() || config.spki_sha256.is_some() {
+                Some(Arc::new(CertMatcher::new(
+                    &config.domain,
+                    config.cert_match,
+                    config.cert_sha256.as_deref(),
+                    config.spki_sha256.as_deref(),
+                )?))
+            } else {
+                None
+            };
+
+        if matcher.0.is_empty() && cert_matcher.is_none() {
+            return Err(
+                "No match rules: set a status code, content or header match, or a match expression"
+                    .into(),
+            );
+        }
+
+        // Prepare TLS connector with SNI set to the target domain. Certificate
+        // matching inspects the certificate itself, so it never verifies it.
+        let may_use_tls = config
+            .ports
+            .iter()
+            .any(|port| config.scheme.tls_for_port(port) != Some(false));
+        let tls = if cert_matcher.is_some() {
+            let connector = tls::build_connector(&CertVerification::Skip)?;
+            Some((connector, tls::server_name(&config.domain)?))
+        } else if may_use_tls {
+            let connector = tls::build_connector(&config.cert_verification)?;
+            Some((connector, tls::server_name(&config.domain)?))
+        } else {
+            None
+        };
+        // Origins often serve self-signed or origin-CA certificates; on request a
+        // failed system root verification is retried without it. Custom roots
+        // were asked for explicitly, so they are never bypassed.
+        let unverified_tls = match config.cert_verification {
+            CertVerification::SystemRoots
+                if config.retry_untrusted && cert_matcher.is_none() && may_use_tls =>
+            {
+                Some(tls::build_connector(&CertVerification::Skip)?)
+            }
+            _ => None,
+        };
+
+        Ok(Self {
+            connect_timeout: config.connect_timeout,
+            read_timeout: config.read_timeout,
+            host_deadline: config.host_deadline,
+            retries: config.retries,
+            stats: Arc::new(ScanStats::default()),
+            workers: config.workers,
+            concurrency: config
+                .adaptive
+                .then(|| Arc::new(AdaptiveLimiter::new(config.workers))),
+            // A resumed scan may already have all the hits it needs
+            stop_flag: Arc::new(AtomicBool::new(config.max_finds == Some(0))),
+            interrupted: Arc::new(AtomicBool::new(false)),
+            found_count: Arc::new(AtomicU64::new(0)),
+            max_finds: config.max_finds,
+            requests: Arc::new(requests),
+            method: Arc::new(config.method),
+            matcher: Arc::new(matcher),
+            max_body,
+            ports: Arc::new(config.ports),
+            scheme: config.scheme,
+            rate_limiter: RateLimiter::new(config.rate, config.subnet_rate).map(Arc::new),
+            interleave_subnets: config.subnet_rate.is_some(),
+            tls,
+            unverified_tls,
+            cert_matcher,
+            two_phase: config.two_phase,
+            events: None,
+            verbose: config.verbose,
+        })
+    }
+
+    /// Scan a single endpoint for the target domain
+    ///
+    /// # Arguments
+    /// * `addr` - IPv4 or IPv6 address and port to scan
+    ///
+    /// # Returns
+    /// * `Some(hit)` - If match found, returns the hit with match details
+    /// * `None` - If no match or connection failed
+    ///
+    /// # Behavior
+    /// - Connects to ip:port via TCP
+    /// - Performs a TLS handshake (SNI = target domain) when the port speaks
+    ///   TLS; in auto mode this is detected once per host by offering a
+    ///   ClientHello and falling back to plaintext if no TLS server answers
+    /// - In certificate mode, matches the peer certificate and stops there
+    /// - Otherwise probes each configured path on a fresh connection, sending
+    ///   the configured HTTP request (HEAD/GET/POST) until one matches
+    /// - Checks for matching status code
+    /// - Optionally validates content with regex
+    /// - Retries probes that failed with a transient error (timeout, reset)
+    ///   up to `retries` times with jittered exponential backoff
+    /// - Gives up on the host once `host_deadline` has passed (the deadline
+    ///   starts with the first connection attempt)
+    ///
+    /// Does not check `stop_flag`: once a scan task has been started it runs
+    /// to completion, so an interrupted scan's checkpoint position is exact.
+    async fn scan_target(&self, addr: SocketAddr) -> Option<ScanHit> {
+        if self.verbose {
+            self.debug(addr, "Scanning".to_string());
+        }
+
+        let mut host = HostState::default();
+        let result = if let Some(ref matcher) = self.cert_matcher {
+            self.probe_with_retries(addr, Probe::Certificate(matcher), &mut host)
+                .await
+        } else {
+            let mut result = Ok(None);
+            for (path, request) in self.requests.iter() {
+                result = self
+                    .probe_with_retries(addr, Probe::Http { path, request }, &mut host)
+                    .await;
+                match result {
+                    Ok(None) | Err(ProbeFailure::Exchange { .. }) => continue,
+                    // A host that can't be connected to won't answer the other paths either
+                    _ => break,
+                }
+            }
+            result
+        };
+
+        match result {
+            Ok(hit) => hit,
+            Err(ProbeFailure::DeadlineExceeded) => {
+                ScanStats::inc(&self.stats.deadline_exceeded);
+                if self.verbose {
+                    self.error(addr, "Host deadline exceeded".to_string());
+                }
+                None
+            }
+            Err(_) => None,
+        }
+    }
+
+    /// Run one probe, retrying transient failures with jittered exponential backoff
+    ///
+    /// Every attempt waits for the rate limiter (if any) first. The host
+    /// deadline is started on the first attempt and, like the detected
+    /// scheme, shared by all probes of the host through `host`.
+    async fn probe_with_retries(
+        &self,
+        addr: SocketAddr,
+        probe: Probe<'_>,
+        host: &mut HostState,
+    ) -> Result<Option<ScanHit>, ProbeFailure> {
+        let mut attempt = 0;
+        loop {
+            // Wait for a send slot under --rate / --subnet-rate
+            if let Some(ref limiter) = self.rate_limiter {
+                limiter.acquire(addr.ip()).await;
+            }
+            if host.deadline.is_none() {
+                host.deadline = self.host_deadline.map(|limit| Instant::now() + limit);
+            }
+
+            match self.probe(addr, &probe, host).await {
+                Err(failure) if failure.is_transient() && attempt < self.retries => {
+                    attempt += 1;
+                    let backoff = retry_backoff(attempt);
+                    if host.deadline.is_some_and(|d| Instant::now() + backoff >= d) {
+                        return Err(ProbeFailure::DeadlineExceeded);
+                    }
+                    ScanStats::inc(&self.stats.retries);
+                    if self.verbose {
+                        self.debug(
+                            addr,
+                            format!(
+                                "Retrying in {}ms (attempt {} of {})",
+                                backoff.as_millis(),
+                                attempt + 1,
+                                self.retries + 1
+                            ),
+                        );
+                    }
+                    tokio::time::sleep(backoff).await;
+                }
+                result => return result,
+            }
+        }
+    }
+
+    /// Connect once and run a single probe over the connection
+    async fn probe(
+        &self,
+        addr: SocketAddr,
+        probe: &Probe<'_>,
+        host: &mut HostState,
+    ) -> Result<Option<ScanHit>, ProbeFailure> {
+        let started = Instant::now();
+
+        match *probe {
+            Probe::TcpOpen => self.tcp_connect(addr, host.deadline).await.map(|_| None),
+            Probe::Certificate(matcher) => match self.connect(addr, host).await? {
+                MaybeTlsStream::Tls(tls_stream) => {
+                    Ok(self.check_certificate(matcher, &tls_stream, addr, started))
+                }
+                MaybeTlsStream::Plain(_) => Ok(None),
+            },
+            Probe::Http { path, request } => {
+                let stream = self.connect(addr, host).await?;
+                let cert_trusted = match stream {
+                    MaybeTlsStream::Tls(_) if self.unverified_tls.is_some() => {
+                        Some(!host.untrusted)
+                    }
+                    _ => None,
+                };
+                let hit = self
+                    .exchange(stream, addr, path, request, started, host.deadline)
+                    .await?;
+                Ok(hit.map(|hit| ScanHit {
+                    cert_trusted,
+                    ..hit
+                }))
+            }
+        }
+    }
+
+    /// Check whether ip:port accepts TCP connections (discovery phase)
+    ///
+    /// Transient failures are retried like any other probe.
+    async fn is_open(&self, addr: SocketAddr) -> bool {
+        let mut host = HostState::default();
+        self.probe_with_retries(addr, Probe::TcpOpen, &mut host)
+            .await
+            .is_ok()
+    }
+
+    /// Open a connection to ip:port, performing the TLS handshake when the port speaks TLS
+    ///
+    /// The TCP connect is bounded by `connect_timeout` and the TLS handshake by
+    /// `read_timeout`, both capped by the host deadline.
+    ///
+    /// When the scheme of the port is not known up front (auto mode on a
+    /// non-standard port), a TLS handshake is offered: a TLS answer means the
+    /// port speaks HTTPS, while a plaintext reply, a closed connection or no
+    /// answer at all means plain HTTP, spoken on a fresh connection. The
+    /// result is remembered in `host`, so the host's other probes skip detection.
+    ///
+    /// # Returns
+    /// * `Ok(stream)` - Connected (and possibly TLS-wrapped) stream
+    /// * `Err` - If the connection or handshake failed or timed out
+    async fn connect(
+        &self,
+        addr: SocketAddr,
+        host: &mut HostState,
+    ) -> Result<MaybeTlsStream, ProbeFailure> {
+        let stream = self.tcp_connect(addr, host.deadline).await?;
+
+        // Certificate matching needs a handshake on every port
+        let wants_tls = if self.cert_matcher.is_some() {
+            Some(true)
+        } else {
+            host.tls.or_else(|| self.scheme.tls_for_port(addr.port()))
+        };
+        let (Some((ref connector, ref server_name)), true) = (&self.tls, wants_tls != Some(false))
+        else {
+            return Ok(MaybeTlsStream::Plain(stream));
+        };
+        let detecting = wants_tls.is_none();
+        let connector = match self.unverified_tls {
+            Some(ref unverified) if host.untrusted => unverified,
+            _ => connector,
+        };
+
+        let handshake_timeout = cap_to_deadline(self.read_timeout, host.deadline);
+        match timeout(
+            handshake_timeout,
+            connector.connect(server_name.clone(), stream),
+        )
+        .await
+        {
+            Ok(Ok(tls_stream)) => {
+                if detecting && self.verbose {
+                    self.debug(addr, "Speaks TLS".to_string());
+                }
+                host.tls = Some(true);
+                Ok(MaybeTlsStream::Tls(Box::new(tls_stream)))
+            }
+            Ok(Err(e)) if detecting && is_plaintext_reply(&e) => {
+                if self.verbose {
+                    self.debug(addr, format!("Doesn't speak TLS ({}), using plain HTTP", e));
+                }
+                self.plaintext_fallback(addr, host).await
+            }
+            Ok(Err(e))
+                if is_certificate_error(&e) && self.unverified_tls.is_some() && !host.untrusted =>
+            {
+                ScanStats::inc(&self.stats.untrusted_certs);
+                if self.verbose {
+                    self.debug(
+                        addr,
+                        format!(
+                            "Untrusted certificate ({}), retrying without verification",
+                            e
+                        ),
+                    );
+                }
+                host.tls = Some(true);
+                host.untrusted = true;
+                self.unverified_fallback(addr, host).await
+            }
+            Ok(Err(e)) => {
+                ScanStats::inc(&self.stats.tls_failures);
+                if self.verbose {
+                    self.error(addr, format!("TLS handshake failed: {}", e));
+                }
+                if detecting && !is_transient(&e) {
+                    // Rejected by a TLS server (certificate, alert, protocol version)
+                    host.tls = Some(true);
+                }
+                Err(ProbeFailure::Connect {
+                    transient: is_transient(&e),
+                })
+            }
+            Err(_) if detecting && past_deadline(host.deadline).is_none() => {
+                // A plaintext server silently waits for a request line it will never get
+                if self.verbose {
+                    self.debug(
+                        addr,
+                        "Didn't answer a TLS handshake, using plain HTTP".to_string(),
+                    );
+                }
+                self.plaintext_fallback(addr, host).await
+            }
+            Err(_) => {
+                ScanStats::inc(&self.stats.read_timeouts);
+                if self.verbose {
+                    self.error(addr, "TLS handshake timeout".to_string());
+                }
+                Err(past_deadline(host.deadline)
+                    .unwrap_or(ProbeFailure::Connect { transient: true }))
+            }
+        }
+    }
+
+    /// Reconnect and redo the handshake without certificate verification
+    ///
+    /// The new connection waits for the rate limiter like any other.
+    async fn unverified_fallback(
+        &self,
+        addr: SocketAddr,
+        host: &mut HostState,
+    ) -> Result<MaybeTlsStream, ProbeFailure> {
+        if let Some(ref limiter) = self.rate_limiter {
+            limiter.acquire(addr.ip()).await;
+        }
+        // `host.untrusted` is set, so this handshake uses the unverified connector
+        Box::pin(self.connect(addr, host)).await
+    }
+
+    /// Remember that the host speaks plain HTTP and reconnect without TLS
+    ///
+    /// The new connection waits for the rate limiter like any other.
+    async fn plaintext_fallback(
+        &self,
+        addr: SocketAddr,
+        host: &mut HostState,
+    ) -> Result<MaybeTlsStream, ProbeFailure> {
+        host.tls = Some(false);
+        if let Some(ref limiter) = self.rate_limiter {
+            limiter.acquire(addr.ip()).await;
+        }
+        self.tcp_connect(addr, host.deadline)
+            .await
+            .map(MaybeTlsStream::Plain)
+    }
+
+    /// Open a plain TCP connection to ip:port within `connect_timeout`
+    async fn tcp_connect(
+        &self,
+        addr: SocketAddr,
+        deadline: Option<Instant>,
+    ) -> Result<TcpStream, ProbeFailure> {
+        // Attempt connection with timeout, reporting the outcome to the concurrency controller.
+        // Running out of local fds/ports says nothing about the target, so those
+        // attempts are retried once the controller has had a chance to back off.
+        let mut attempt = 0;
+        let stream = loop {
+            ScanStats::inc(&self.stats.connect_attempts);
+            let started = Instant::now();
+            let connect_timeout = cap_to_deadline(self.connect_timeout, deadline);
+            let (result, outcome) = match timeout(connect_timeout, TcpStream::connect(addr)).await {
+                Ok(Ok(stream)) => {
+                    ScanStats::inc(&self.stats.connected);
+                    (Ok(stream), ConnectOutcome::Connected(started.elapsed()))
+                }
+                Ok(Err(e)) => {
+                    ScanStats::inc(&self.stats.connect_errors);
+                    if self.verbose {
+                        self.error(addr, format!("Connection failed: {}", e));
+                    }
+                    let failure = ProbeFailure::Connect {
+                        transient: is_transient(&e),
+                    };
+                    (Err(failure), ConnectOutcome::from_error(&e))
+                }
+                Err(_) => {
+                    ScanStats::inc(&self.stats.connect_timeouts);
+                    if self.verbose {
+                        self.error(addr, "Connection timeout".to_string());
+                    }
+                    let failure = ProbeFailure::Connect { transient: true };
+                    (Err(failure), ConnectOutcome::TimedOut)
+                }
+            };
+            if let Some(ref concurrency) = self.concurrency {
+                concurrency.record(outcome);
+            }
+
+            attempt += 1;
+            if outcome == ConnectOutcome::ResourceExhausted && attempt < RESOURCE_RETRIES {
+                tokio::time::sleep(Duration::from_millis(100 * attempt as u64)).await;
+                continue;
+            }
+            break result.map_err(|failure| past_deadline(deadline).unwrap_or(failure))?;
+        };
+
+        // Disable Nagle's algorithm for faster small packets
+        let _ = stream.set_nodelay(true);
+        Ok(stream)
+    }
+
+    /// Match the certificate presented during the TLS handshake
+    fn check_certificate(
+        &self,
+        matcher: &CertMatcher,
+        tls_stream: &TlsStream<TcpStream>,
+        addr: SocketAddr,
+        started: Instant,
+    ) -> Option<ScanHit> {
+        let leaf = tls_stream.get_ref().1.peer_certificates()?.first()?;
+
+        match matcher.check(leaf) {
+            Some(rule) => Some(ScanHit {
+                ip: addr.ip(),
+                port: addr.port(),
+                scheme: "https".to_string(),
+                path: None,
+                status: None,
+                matched: vec![rule],
+                response_time_ms: started.elapsed().as_millis() as u64,
+                excerpt: String::new(),
+                cert_trusted: None,
+            }),
+            None => {
+                if self.verbose {
+                    self.debug(
+                        addr,
+                        "Presented a certificate that didn't match".to_string(),
+                    );
+                }
+                None
+            }
+        }
+    }
+
+    /// Send the HTTP request for one path over an established stream and
+    /// check the response against the match rules
+    ///
+    /// Works for both plain TCP and TLS streams.
+    ///
+    /// # Returns
+    /// * `Ok(Some(hit))` - The response matched
+    /// * `Ok(None)` - A response arrived but did not match
+    /// * `Err` - If the request could not be sent or no response arrived in time
+    async fn exchange(
+        &self,
+        mut stream: MaybeTlsStream,
+        addr: SocketAddr,
+        path: &str,
+        request: &[u8],
+        started: Instant,
+        deadline: Option<Instant>,
+    ) -> Result<Option<ScanHit>, ProbeFailure> {
+        let scheme = stream.scheme();
+
+        // Send HTTP request
+        if let Err(e) = stream.write_all(request).await {
+            if self.verbose {
+                self.error(addr, format!("Failed to write: {}", e));
+            }
+            return Err(ProbeFailure::Exchange {
+                transient: is_transient(&e),
+            });
+        }
+
+        // Read and parse the full response (headers plus body up to the limit).
+        // The whole read loop shares one deadline; a body cut off by it is still matched.
+        let head_request = self.method.as_str() == "HEAD";
+        let read_deadline =
+            tokio::time::Instant::now() + cap_to_deadline(self.read_timeout, deadline);
+        let response = match http::read_response(
+            &mut stream,
+            head_request,
+            self.max_body,
+            read_deadline,
+        )
+        .await
+        {
+            Ok(response) => response,
+            Err(e) if e.kind() == std::io::ErrorKind::TimedOut => {
+                ScanStats::inc(&self.stats.read_timeouts);
+                if self.verbose {
+                    self.error(addr, "Read timeout".to_string());
+                }
+                return Err(
+                    past_deadline(deadline).unwrap_or(ProbeFailure::Exchange { transient: true })
+                );
+            }
+            Err(e) => {
+                if self.verbose {
+                    self.error(addr, format!("Failed to read: {}", e));
+                }
+                return Err(ProbeFailure::Exchange {
+                    transient: is_transient(&e),
+                });
+            }
+        };
+
+        let input = MatchInput {
+            response: &response,
+            certificate: stream.peer_certificate(),
+        };
+        if let Some(matched) = self.matcher.evaluate(&input) {
+            return Ok(Some(
+                self.http_hit(addr, scheme, path, &response, matched, started),
+            ));
+        }
+
+        if self.verbose {
+            self.debug(
+                addr,
+                format!("{} returned {} but didn't match", path, response.status),
+            );
+        }
+
+        Ok(None)
+    }
+
+    /// Build a hit record from a matching HTTP response
+    fn http_hit(
+        &self,
+        addr: SocketAddr,
+        scheme: &str,
+        path: &str,
+        response: &HttpResponse,
+        matched: Vec<String>,
+        started: Instant,
+    ) -> ScanHit {
+        let body = response.body_text();
+        let excerpt: String = body
+            .split_whitespace()
+            .collect::<Vec<_>>()
+            .join(" ")
+            .chars()
+            .take(EXCERPT_LEN)
+            .collect();
+
+        ScanHit {
+            ip: addr.ip(),
+            port: addr.port(),
+            scheme: scheme.to_string(),
+            path: Some(path.to_string()),
+            status: Some(response.status),
+            matched,
+            response_time_ms: started.elapsed().as_millis() as u64,
+            excerpt,
+            cert_trusted: None,
+        }
+    }
+
+    /// Whether the scan has been told to stop (match limit reached or interrupted)
+    fn is_stopped(&self) -> bool {
+        self.stop_flag.load(Ordering::Relaxed)
+    }
+
+    /// Whether the scan was cancelled through its token
+    fn is_interrupted(&self) -> bool {
+        self.interrupted.load(Ordering::Relaxed)
+    }
+
+    /// Stop handing out new targets; in-flight scans are allowed to finish
+    fn interrupt(&self) {
+        self.interrupted.store(true, Ordering::Relaxed);
+        self.stop_flag.store(true, Ordering::Relaxed);
+    }
+
+    /// Count a hit against `max_finds`, tripping the stop flag at the limit
+    ///
+    /// # Returns
+    /// * `true` - The hit should be reported
+    /// * `false` - The limit was already reached by concurrent hits; drop it
+    fn accept_hit(&self) -> bool {
+        let found = self.found_count.fetch_add(1, Ordering::Relaxed) + 1;
+        match self.max_finds {
+            Some(limit) if found > limit => false,
+            Some(limit) if found == limit => {
+                self.stop_flag.store(true, Ordering::Relaxed);
+                true
+            }
+            _ => true,
+        }
+    }
+
+    /// Send an event to the consumer of [`Scanner::scan`]
+    ///
+    /// Events are dropped if the consumer has gone away; the scan then
+    /// simply runs to its end.
+    fn emit(&self, event: ScanEvent) {
+        if let Some(ref events) = self.events {
+            let _ = events.send(event);
+        }
+    }
+
+    /// Report a failed target (callers check `verbose`)
+    fn error(&self, target: SocketAddr, message: String) {
+        self.emit(ScanEvent::Error { target, message });
+    }
+
+    /// Report diagnostic detail about a target (callers check `verbose`)
+    fn debug(&self, target: SocketAddr, message: String) {
+        self.emit(ScanEvent::Debug { target, message });
+    }
+
+    /// Run the scan, reporting progress and hits as a stream of events
+    ///
+    /// # Arguments
+    /// * `targets` - Ranges (optionally resumed after `skip` targets) or explicit endpoints
+    /// * `cancel` - Cancelling the token stops handing out new targets; in-flight
+    ///   targets finish, so [`ScanSummary::position`] is exact for resuming
+    ///
+    /// # Returns
+    /// * A stream of [`ScanEvent`]s that always ends with [`ScanEvent::Finished`]
+    ///   (or [`ScanEvent::Failed`] if the targets are invalid or the scan task panicked)
+    ///
+    /// # Behavior
+    /// - The scan runs on a spawned task, so this must be called from within
+    ///   a Tokio runtime; it makes progress whether or not the stream is polled
+    /// - Range scans with `two_phase` set first discover open endpoints with
+    ///   TCP connects only, then probe just those
+    /// - Dropping the stream does not stop the scan; cancel the token for that
+    pub fn scan(
+        mut self,
+        targets: Targets,
+        cancel: CancellationToken,
+    ) -> impl Stream<Item = ScanEvent> + Send + 'static {
+        let (events, receiver) = mpsc::unbounded_channel();
+        self.events = Some(events);
+
+        tokio::spawn(async move {
+            if let Err(e) = targets.validate() {
+                self.emit(ScanEvent::Failed(e));
+                return;
+            }
+
+            // Background tasks hold event senders too; the set aborts them
+            // when dropped, so the stream ends even if the scan panics
+            let mut background = JoinSet::new();
+            {
+                let scanner = self.clone();
+                background.spawn(async move {
+                    cancel.cancelled().await;
+                    scanner.interrupt();
+                });
+            }
+            let execute = {
+                let scanner = self.clone();
+                tokio::spawn(async move { scanner.execute(targets).await })
+            };
+            let result = execute.await;
+            drop(background);
+            match result {
+                Ok(summary) => self.emit(ScanEvent::Finished(summary)),
+                Err(e) => self.emit(ScanEvent::Failed(e.to_string())),
+            }
+        });
+
+        stream::unfold(receiver, |mut receiver| async move {
+            receiver.recv().await.map(|event| (event, receiver))
+        })
+    }
+
+    /// Scan `targets` to completion and summarise the outcome
+    async fn execute(&self, targets: Targets) -> ScanSummary {
+        let started = Instant::now();
+
+        let (hits, position, ranges) = match targets {
+            Targets::Endpoints(endpoints) => {
+                let (hits, position) = self.scan_endpoints(&endpoints).await;
+                (hits, position, None)
+            }
+            Targets::Ranges { ranges, skip } => {
+                let tracker = Arc::new(RangeTracker::new(
+                    &ranges,
+                    |network| self.range_target_count(network),
+                    if self.two_phase { 0 } else { skip },
+                ));
+                if self.two_phase {
+                    let mut open = self.discover_ranges(tracker.clone()).await;
+                    open.sort();
+                    self.emit(ScanEvent::Discovered(open.clone()));
+                    let (hits, position) = if self.is_stopped() || open.is_empty() {
+                        (Vec::new(), 0)
+                    } else {
+                        self.scan_endpoints(&open).await
+                    };
+                    (hits, position, Some(tracker))
+                } else {
+                    let (hits, position) = self.scan_ranges(tracker.clone(), skip).await;
+                    (hits, position, Some(tracker))
+                }
+            }
+        };
+
+        ScanSummary {
+            hits,
+            position,
+            cancelled: self.is_interrupted(),
+            limit_reached: self.is_stopped() && !self.is_interrupted(),
+            stats: self.stats.clone(),
+            ranges,
+            elapsed: started.elapsed(),
+        }
+    }
+
+    /// Scan every range (CIDR notation) on every configured port in one pipeline
+    ///
+    /// # Arguments
+    /// * `ranges` - Counters for the ranges to scan, in scan order
+    /// * `skip` - Number of leading targets already scanned (when resuming)
+    ///
+    /// # Returns
+    /// * Hits for all matches found, and the number of targets (including
+    ///   `skip`) that have been fully scanned
+    ///
+    /// # Behavior
+    /// - Chains the (ip, port) pairs of all ranges lazily into one target
+    ///   stream (nothing is materialised), so workers move straight on to the
+    ///   next range instead of idling on the slowest hosts of the previous one
+    /// - Creates concurrent scan tasks (up to `workers` parallel)
+    /// - Reports progress for the whole scan with the number of finished
+    ///   ranges, and reports each range as its last target completes
+    /// - Stops early once `max_finds` matches have been found
+    /// - When interrupted, stops dispatching and drains in-flight tasks
+    async fn scan_ranges(&self, ranges: Arc<RangeTracker>, skip: u64) -> (Vec<ScanHit>, u64) {
+        let networks: Vec<IpNetwork> = ranges.ranges().iter().map(|r| r.network).collect();
+        let targets = chained_targets(&networks, &self.ports, self.interleave_subnets, skip);
+        let total = ranges.ranges().iter().map(|r| r.total).sum();
+        let found = Arc::new(Mutex::new(Vec::new()));
+        let found_clone = found.clone();
+        let tracker = ranges.clone();
+        let scanned = self
+            .run_targets(
+                Phase::Probe,
+                targets,
+                total,
+                skip,
+                Some(ranges),
+                move |scanner, (index, addr)| {
+                    let found = found_clone.clone();
+                    let tracker = tracker.clone();
+                    async move {
+                        if let Some(hit) = scanner.scan_target(addr).await {
+                            if scanner.report_hit(hit, &found) {
+                                tracker.found(index);
+                            }
+                        }
+                        if let Some(range) = tracker.complete(index) {
+                            scanner.emit(ScanEvent::RangeFinished {
+                                phase: Phase::Probe,
+                                network: range.network,
+                                total: range.total,
+                                found: range.found.load(Ordering::Relaxed),
+                            });
+                        }
+                    }
+                },
+            )
+            .await;
+
+        let found = found.lock().unwrap().clone();
+        (found, skip + scanned)
+    }
+
+    /// Probe a list of endpoints, e.g. those found by discovery
+    ///
+    /// # Returns
+    /// * Hits for all matches found, and the number of endpoints fully scanned
+    async fn scan_endpoints(&self, endpoints: &[SocketAddr]) -> (Vec<ScanHit>, u64) {
+        let found = Arc::new(Mutex::new(Vec::new()));
+        let found_clone = found.clone();
+        let targets = endpoints.iter().copied();
+        let scanned = self
+            .run_targets(
+                Phase::Probe,
+                targets,
+                endpoints.len() as u64,
+                0,
+                None,
+                move |scanner, addr| {
+                    let found = found_clone.clone();
+                    async move {
+                        if let Some(hit) = scanner.scan_target(addr).await {
+                            scanner.report_hit(hit, &found);
+                        }
+                    }
+                },
+            )
+            .await;
+
+        let found = found.lock().unwrap().clone();
+        (found, scanned)
+    }
+
+    /// Find the endpoints of all ranges that accept TCP connections on the scan
+    /// ports (first phase of a two-phase scan), in one pipeline like `scan_ranges`
+    ///
+    /// # Returns
+    /// * Open endpoints, in no particular order
+    async fn discover_ranges(&self, ranges: Arc<RangeTracker>) -> Vec<SocketAddr> {
+        let networks: Vec<IpNetwork> = ranges.ranges().iter().map(|r| r.network).collect();
+        let targets = chained_targets(&networks, &self.ports, self.interleave_subnets, 0);
+        let total = ranges.ranges().iter().map(|r| r.total).sum();
+        let open = Arc::new(Mutex::new(Vec::new()));
+        let open_clone = open.clone();
+        let tracker = ranges.clone();
+        self.run_targets(
+            Phase::Discovery,
+            targets,
+            total,
+            0,
+            Some(ranges),
+            move |scanner, (index, addr)| {
+                let open = open_clone.clone();
+                let tracker = tracker.clone();
+                async move {
+                    if scanner.is_open(addr).await {
+                        if scanner.verbose {
+                            scanner.debug(addr, "Open".to_string());
+                        }
+                        open.lock().unwrap().push(addr);
+                        tracker.found(index);
+                    }
+                    if let Some(range) = tracker.complete(index) {
+                        scanner.emit(ScanEvent::RangeFinished {
+                            phase: Phase::Discovery,
+                            network: range.network,
+                            total: range.total,
+                            found: range.found.load(Ordering::Relaxed),
+                        });
+                    }
+                }
+            },
+        )
+        .await;
+
+        let open = open.lock().unwrap().clone();
+        open
+    }
+
+    /// Number of targets in a range: every address on every port
+    fn range_target_count(&self, network: &IpNetwork) -> u64 {
+        range_size(network).saturating_mul(self.ports.count() as u64)
+    }
+
+    /// Report and collect a hit, unless the match limit was already reached
+    ///
+    /// # Returns
+    /// * `true` - The hit was reported
+    /// * `false` - The match limit was already reached; the hit was dropped
+    fn report_hit(&self, hit: ScanHit, found: &Mutex<Vec<ScanHit>>) -> bool {
+        if !self.accept_hit() {
+            return false;
+        }
+
+        self.emit(ScanEvent::Hit(hit.clone()));
+        found.lock().unwrap().push(hit);
+        true
+    }
+
+    /// Run `task` for every target with up to `workers` tasks in flight
+    ///
+    /// # Arguments
+    /// * `phase` - Phase reported in the progress events
+    /// * `targets` - Endpoints to process, consumed lazily as workers free up
+    /// * `total` - Size of the whole target set, for progress events
+    /// * `skip` - Targets already done before this call (when resuming)
+    /// * `ranges` - Per-range counters to include in the progress status, if any
+    /// * `task` - Work to run per target
+    ///
+    /// # Returns
+    /// * Number of targets dispatched (all of them ran to completion unless
+    ///   the match limit was reached)
+    ///
+    /// # Behavior
+    /// - Emits `PhaseStarted`, then `Progress` every `PROGRESS_INTERVAL`
+    ///   including finished ranges and the adaptive controller's decisions
+    /// - Holds a concurrency slot per target when adaptive concurrency is on
+    /// - Stops dispatching once the scan is stopped; after an interrupt it
+    ///   keeps draining so every dispatched target completes
+    async fn run_targets<T, I, F, Fut>(
+        &self,
+        phase: Phase,
+        targets: I,
+        total: u64,
+        skip: u64,
+        ranges: Option<Arc<RangeTracker>>,
+        task: F,
+    ) -> u64
+    where
+        I: Iterator<Item = T>,
+        F: Fn(Scanner, T) -> Fut,
+        Fut: Future<Output = ()>,
+    {
+        self.emit(ScanEvent::PhaseStarted { phase, total, skip });
+
+        let completed = Arc::new(AtomicU64::new(0));
+        let concurrency = self.concurrency.clone();
+        let progress = {
+            let completed = completed.clone();
+            move || {
+                let mut parts = Vec::new();
+                if let Some(ref ranges) = ranges {
+                    parts.push(ranges.status());
+                }
+                if let Some(ref concurrency) = concurrency {
+                    parts.push(concurrency.decision());
+                }
+                ScanEvent::Progress {
+                    phase,
+                    done: skip + completed.load(Ordering::Relaxed),
+                    total,
+                    status: parts.join(" | "),
+                }
+            }
+        };
+
+        // Periodically report progress and let the controller resize concurrency.
+        // The set aborts the ticker when dropped, including on a panic.
+        let mut ticker = JoinSet::new();
+        {
+            let scanner = self.clone();
+            let progress = progress.clone();
+            ticker.spawn(async move {
+                let mut interval = tokio::time::interval(PROGRESS_INTERVAL);
+                let mut last_adjust = Instant::now();
+                loop {
+                    interval.tick().await;
+                    if let Some(ref concurrency) = scanner.concurrency {
+                        if last_adjust.elapsed() >= ADJUST_INTERVAL {
+                            concurrency.adjust();
+                            last_adjust = Instant::now();
+                        }
+                    }
+                    scanner.emit(progress());
+                }
+            });
+        }
+
+        let dispatched = AtomicU64::new(0);
+
+        // Create stream of tasks. Targets are generated lazily as workers
+        // free up, so memory stays flat no matter how large the range is.
+        // Nothing new is dispatched once the scan has been stopped.
+        let mut stream = stream::iter(targets)
+            .take_while(|_| futures::future::ready(!self.is_stopped()))
+            .map(|target| {
+                dispatched.fetch_add(1, Ordering::Relaxed);
+                let scanner = self.clone();
+                let completed = completed.clone();
+                let work = task(self.clone(), target);
+
+                async move {
+                    let slot = match scanner.concurrency {
+                        Some(ref concurrency) => Some(concurrency.acquire().await),
+                        None => None,
+                    };
+                    work.await;
+                    drop(slot);
+                    completed.fetch_add(1, Ordering::Relaxed);
+                }
+            })
+            .buffer_unordered(self.workers);
+
+        // Process results. On an interrupt keep draining so every dispatched
+        // target completes; on reaching the match limit drop in-flight work.
+        while (stream.next().await).is_some() {
+            if self.is_stopped() && !self.is_interrupted() {
+                break;
+            }
+        }
+        drop(stream);
+        ticker.abort_all();
+        self.emit(progress());
+
+        dispatched.load(Ordering::Relaxed)
+    }
+}
+
+/// Every (ip, port) pair of a range after the first `skip`
+///
+/// Targets go address by address, every port of an address in turn. With
+/// `interleave`, consecutive targets instead take turns across the range's
+/// subnets (see [`rate::subnet_count`]), so a per-subnet rate limit holds
+/// back each subnet rather than the whole scan. Either way the order is
+/// fixed and each target is computed from its index, so resuming deep into
+/// a large range costs nothing.
+fn range_targets(
+    network: &IpNetwork,
+    ports: Arc<[u16]>,
+    interleave: bool,
+    skip: u64,
+) -> impl Iterator<Item = SocketAddr> {
+    let per_ip = ports.len() as u64;
+    let size = range_size(network);
+    let subnets = if interleave {
+        rate::subnet_count(network)
+    } else {
+        1
+    };
+    let subnet_size = size / subnets;
+    let network = *network;
+    (skip..size.saturating_mul(per_ip)).map(move |index| {
+        let (subnet, rest) = (index % subnets, index / subnets);
+        let ip = nth_address(&network, subnet * subnet_size + rest / per_ip);
+        SocketAddr::new(ip, ports[(rest % per_ip) as usize])
+    })
+}
+
+/// Address `offset` places after the network address of a range
+fn nth_address(network: &IpNetwork, offset: u64) -> IpAddr {
+    match network {
+        IpNetwork::V4(net) => IpAddr::V4(Ipv4Addr::from(
+            u32::from(net.network()).wrapping_add(offset as u32),
+        )),
+        IpNetwork::V6(net) => IpAddr::V6(Ipv6Addr::from(
+            u128::from(net.network()).wrapping_add(u128::from(offset)),
+        )),
+    }
+}
+
+/// Targets of all `networks` chained range after range, after the first `skip`
+///
+/// Each target is tagged with the index of its range. Ranges that lie
+/// entirely within `skip` are passed over without iterating them, and with
+/// `interleave` each range's targets alternate between its subnets.
+fn chained_targets(
+    networks: &[IpNetwork],
+    ports: &PortList,
+    interleave: bool,
+    skip: u64,
+) -> impl Iterator<Item = (usize, SocketAddr)> {
+    // One port list shared by every address
+    let ports: Arc<[u16]> = ports.iter().collect();
+    let per_ip = ports.len() as u64;
+    let mut remaining = skip;
+    let mut pending = Vec::new();
+    for (index, network) in networks.iter().enumerate() {
+        let size = range_size(network).saturating_mul(per_ip);
+        if remaining >= size {
+            remaining -= size;
+            continue;
+        }
+        pending.push((index, *network, remaining));
+        remaining = 0;
+    }
+
+    pending.into_iter().flat_map(move |(index, network, skip)| {
+        range_targets(&network, ports.clone(), interleave, skip).map(move |addr| (index, addr))
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::matcher::MatchInput;
+    use tokio::net::TcpListener;
+
+    /// A matcher with a bug: panics on every response
+    #[derive(Debug)]
+    struct PanickingMatcher;
+
+    impl Matcher for PanickingMatcher {
+        fn evaluate(&self, _input: &MatchInput<'_>) -> Option<Vec<String>> {
+            panic!("matcher bug");
+        }
+
+        fn describe(&self) -> String {
+            "panic".to_string()
+        }
+    }
+
+    /// Serve `response` to every connection on a local port
+    async fn serve(response: &'static str) -> SocketAddr {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let addr = listener.local_addr().unwrap();
+        tokio::spawn(async move {
+            while let Ok((mut socket, _)) = listener.accept().await {
+                tokio::spawn(async move {
+                    let mut request = [0u8; 1024];
+                    let _ = tokio::io::AsyncReadExt::read(&mut socket, &mut request).await;
+                    let _ = socket.write_all(response.as_bytes()).await;
+                });
+            }
+        });
+        addr
+    }
+
+    #[tokio::test]
+    async fn panicking_scan_ends_the_stream() {
+        let addr = serve("HTTP/1.1 202 Accepted\r\nContent-Length: 0\r\n\r\n").await;
+        let config = ScannerConfig::builder("example.com")
+            .ports(addr.port().to_string().parse().unwrap())
+            .scheme(Scheme::Http)
+            .matcher(PanickingMatcher)
+            .build();
+        let scanner = Scanner::new(config).unwrap();
+
+        let events = scanner.scan(Targets::Endpoints(vec![addr]), CancellationToken::new());
+        let events: Vec<ScanEvent> = timeout(Duration::from_secs(10), events.collect())
+            .await
+            .expect("event stream never ended");
+        assert!(matches!(events.last(), Some(ScanEvent::Failed(_))));
+    }
+
+    #[tokio::test]
+    async fn status_only_hits_still_carry_an_excerpt() {
+        let addr = serve(
+            "HTTP/1.1 202 Accepted\r\nContent-Length: 28\r\n\r\n<h1>Example\n\n   origin</h1>\n",
+        )
+        .await;
+        let config = ScannerConfig::builder("example.com")
+            .ports(addr.port().to_string().parse().unwrap())
+            .scheme(Scheme::Http)
+            .method("GET")
+            .build();
+        let scanner = Scanner::new(config).unwrap();
+
+        let events = scanner.scan(Targets::Endpoints(vec![addr]), CancellationToken::new());
+        let events: Vec<ScanEvent> = timeout(Duration::from_secs(10), events.collect())
+            .await
+            .expect("event stream never ended");
+        let hit = events
+            .iter()
+            .find_map(|event| match event {
+                ScanEvent::Hit(hit) => Some(hit),
+                _ => None,
+            })
+            .expect("no hit");
+        assert_eq!(hit.excerpt, "<h1>Example origin</h1>");
+
+        // Result files always have the field, like the CSV column
+        let record = serde_json::to_value(hit).unwrap();
+        assert_eq!(record["cert_trusted"], serde_json::Value::Null);
+    }
+
+    #[tokio::test]
+    async fn oversized_ipv6_range_fails_the_scan() {
+        let scanner = Scanner::new(ScannerConfig::builder("example.com").build()).unwrap();
+        let targets = Targets::Ranges {
+            ranges: vec!["2001:db8::/64".parse().unwrap()],
+            skip: 0,
+        };
+
+        let events: Vec<ScanEvent> = scanner
+            .scan(targets, CancellationToken::new())
+            .collect()
+            .await;
+        assert!(matches!(events.as_slice(), [ScanEvent::Failed(_)]));
+    }
+
+    /// Every target of `networks` in scan order, enumerated the obvious way
+    fn all_targets(networks: &[IpNetwork], ports: &PortList) -> Vec<(usize, SocketAddr)> {
+        let mut targets = Vec::new();
+        for (index, network) in networks.iter().enumerate() {
+            for ip in network.iter() {
+                for port in ports.iter() {
+                    targets.push((index, SocketAddr::new(ip, port)));
+                }
+            }
+        }
+        targets
+    }
+
+    fn chained(networks: &[IpNetwork], ports: &PortList, skip: u64) -> Vec<(usize, SocketAddr)> {
+        chained_targets(networks, ports, false, skip).collect()
+    }
+
+    fn networks() -> Vec<IpNetwork> {
+        vec![
+            "10.0.0.0/30".parse().unwrap(),
+            "10.0.1.0/31".parse().unwrap(),
+            "2001:db8::/127".parse().unwrap(),
+        ]
+    }
+
+    #[test]
+    fn chained_targets_without_skip_cover_every_range() {
+        let ports: PortList = "80,443".parse().unwrap();
+        let targets = chained(&networks(), &ports, 0);
+        assert_eq!(targets.len(), (4 + 2 + 2) * 2);
+        assert_eq!(targets, all_targets(&networks(), &ports));
+        assert_eq!(targets[0], (0, "10.0.0.0:80".parse().unwrap()));
+        assert_eq!(targets[1], (0, "10.0.0.0:443".parse().unwrap()));
+    }
+
+    #[test]
+    fn skip_on_a_range_boundary_starts_the_next_range() {
+        let ports: PortList = "80,443".parse().unwrap();
+        let targets = chained(&networks(), &ports, 8);
+        assert_eq!(targets[0], (1, "10.0.1.0:80".parse().unwrap()));
+        assert_eq!(targets, all_targets(&networks(), &ports)[8..]);
+    }
+
+    #[test]
+    fn skip_inside_the_second_range_resumes_mid_address() {
+        let ports: PortList = "80,443".parse().unwrap();
+        let targets = chained(&networks(), &ports, 11);
+        assert_eq!(targets[0], (1, "10.0.1.1:443".parse().unwrap()));
+        assert_eq!(targets, all_targets(&networks(), &ports)[11..]);
+    }
+
+    #[test]
+    fn skip_at_or_beyond_the_total_leaves_nothing() {
+        let ports: PortList = "80,443".parse().unwrap();
+        assert!(chained(&networks(), &ports, 16).is_empty());
+        assert!(chained(&networks(), &ports, 17).is_empty());
+        assert!(chained(&networks(), &ports, u64::MAX).is_empty());
+    }
+
+    #[test]
+    fn every_skip_resumes_exactly_after_the_skipped_targets() {
+        for ports in ["80", "80,443", "80,443,8080"] {
+            let ports: PortList = ports.parse().unwrap();
+            let all = all_targets(&networks(), &ports);
+            for skip in 0..=all.len() {
+                assert_eq!(
+                    chained(&networks(), &ports, skip as u64),
+                    all[skip..],
+                    "ports {} skip {}",
+                    ports,
+                    skip
+                );
+            }
+        }
+    }
+
+    #[test]
+    fn skip_deep_into_a_large_range_is_computed_directly() {
+        let ports: PortList = "80,443".parse().unwrap();
+        let networks: Vec<IpNetwork> = vec!["10.0.0.0/8".parse().unwrap()];
+        let mut targets = chained_targets(&networks, &ports, false, 2 * 16_000_000 + 1);
+        assert_eq!(
+            targets.next(),
+            Some((0, "10.244.36.0:443".parse().unwrap()))
+        );
+        assert_eq!(targets.next(), Some((0, "10.244.36.1:80".parse().unwrap())));
+
+        // Host bits in the CIDR are ignored, like when iterating the network
+        let networks: Vec<IpNetwork> = vec!["10.0.0.5/30".parse().unwrap()];
+        assert_eq!(
+            chained(&networks, &ports, 0),
+            all_targets(&networks, &ports)
+        );
+        assert_eq!(
+            chained(&networks, &ports, 0)[0].1,
+            "10.0.0.4:80".parse().unwrap()
+        );
+    }
+
+    #[test]
+    fn interleaved_targets_take_turns_across_subnets() {
+        let ports: PortList = "80,443".parse().unwrap();
+        let networks: Vec<IpNetwork> = vec![
+            "10.0.0.0/23".parse().unwrap(),
+            "10.0.2.0/31".parse().unwrap(),
+        ];
+        let interleaved: Vec<(usize, SocketAddr)> =
+            chained_targets(&networks, &ports, true, 0).collect();
+        let first: Vec<SocketAddr> = interleaved[..5].iter().map(|&(_, addr)| addr).collect();
+        let expected: Vec<SocketAddr> = [
+            "10.0.0.0:80",
+            "10.0.1.0:80",
+            "10.0.0.0:443",
+            "10.0.1.0:443",
+            "10.0.0.1:80",
+        ]
+        .iter()
+        .map(|addr| addr.parse().unwrap())
+        .collect();
+        assert_eq!(first, expected);
+
+        // Same targets as the address order, each range still after the previous one
+        let mut sorted = interleaved.clone();
+        sorted.sort();
+        let mut all = all_targets(&networks, &ports);
+        all.sort();
+        assert_eq!(sorted, all);
+        assert_eq!(interleaved[1024..], all_targets(&networks, &ports)[1024..]);
+
+        for skip in 0..=interleaved.len() {
+            let resumed: Vec<(usize, SocketAddr)> =
+                chained_targets(&networks, &ports, true, skip as u64).collect();
+            assert_eq!(resumed, interleaved[skip..], "skip {}", skip);
+        }
+    }
+
+    #[test]
+    fn subnets_of_a_range_are_counted_by_prefix() {
+        let count = |range: &str| rate::subnet_count(&range.parse().unwrap());
+        assert_eq!(count("10.0.0.0/16"), 256);
+        assert_eq!(count("10.0.0.0/24"), 1);
+        assert_eq!(count("10.0.0.0/30"), 1);
+        assert_eq!(count("2001:db8::/112"), 1);
+    }
+
+    #[test]
+    fn range_tracker_agrees_with_the_skip() {
+        let ports: PortList = "80,443".parse().unwrap();
+        let tracker = RangeTracker::new(&networks(), |n| range_size(n) * 2, 11);
+        let done: Vec<u64> = tracker
+            .ranges()
+            .iter()
+            .map(|r| r.done.load(Ordering::Relaxed))
+            .collect();
+        assert_eq!(done, vec![8, 3, 0]);
+        assert_eq!(tracker.finished(), 1);
+
+        // The first target handed out belongs to the first unfinished range
+        let (index, _) = chained(&networks(), &ports, 11)[0];
+        assert_eq!(index, 1);
+    }
+
+    #[test]
+    fn certificate_errors_are_told_apart() {
+        use tokio_rustls::rustls::{self, CertificateError};
+        let tls_error = |e: rustls::Error| std::io::Error::new(std::io::ErrorKind::InvalidData, e);
+
+        assert!(is_certificate_error(&tls_error(
+            rustls::Error::InvalidCertificate(CertificateError::UnknownIssuer)
+        )));
+        assert!(is_certificate_error(&tls_error(
+            rustls::Error::InvalidCertificate(CertificateError::NotValidForName)
+        )));
+        assert!(!is_certificate_error(&tls_error(
+            rustls::Error::HandshakeNotComplete
+        )));
+        assert!(!is_certificate_error(&std::io::Error::from(
+            std::io::ErrorKind::ConnectionReset
+        )));
+    }
+
+    /// Self-signed CA unrelated to any server
+    const TEST_CA: &str = "-----BEGIN CERTIFICATE-----\n\
+MIIBjzCCATWgAwIBAgIUcsoIs5vNZRZsrbtcY4Gk1pPtBZ8wCgYIKoZIzj0EAwIw\n\
+HDEaMBgGA1UEAwwRT2N0b0ludGVsIFRlc3QgQ0EwIBcNMjYxMDE2MjMwMDAxWhgP\n\
+MjEyNjA5MjIyMzAwMDFaMBwxGjAYBgNVBAMMEU9jdG9JbnRlbCBUZXN0IENBMFkw\n\
+EwYHKoZIzj0CAQYIKoZIzj0DAQcDQgAEWONPwiITa4xOI9uIJETuQmNk3vRHtJ5B\n\
+qbL+kQPd+2NoQeyUMb2K7SQGXSPhcLbwbQCTpNKi0Ge+aDcZPIRHIaNTMFEwHQYD\n\
+VR0OBBYEFLl3eSG0YMqEXA14Aw6BrOVKqY30MB8GA1UdIwQYMBaAFLl3eSG0YMqE\n\
+XA14Aw6BrOVKqY30MA8GA1UdEwEB/wQFMAMBAf8wCgYIKoZIzj0EAwIDSAAwRQIh\n\
+APJuf+Bao/O+k1z/mpOyrUubfvgyeOpeT9iklbGOmo+MAiBLxY9xd34SL29UIM3j\n\
+eCURHPx8dp/mHOyKadkB7gO0Ug==\n\
+-----END CERTIFICATE-----\n";
+
+    #[test]
+    fn untrusted_certificates_are_only_retried_on_request() {
+        let retries = |builder: ScannerConfigBuilder| {
+            let config = builder.ports("443".parse().unwrap()).build();
+            Scanner::new(config).unwrap().unverified_tls.is_some()
+        };
+        let builder = || ScannerConfig::builder("example.com");
+        assert!(!retries(builder()));
+        assert!(!retries(
+            builder()
+                .cert_verification(CertVerification::Skip)
+                .retry_untrusted(true)
+        ));
+
+        // Custom roots were asked for explicitly and are never bypassed
+        let path = std::env::temp_dir().join(format!("octointel-ca-{}.pem", std::process::id()));
+        std::fs::write(&path, TEST_CA).unwrap();
+        let custom = retries(
+            builder()
+                .cert_verification(CertVerification::CustomRoots(path.clone()))
+                .retry_untrusted(true),
+        );
+        std::fs::remove_file(&path).unwrap();
+        assert!(!custom);
+    }
+
+    #[test]
+    fn builder_drops_the_default_status_like_the_cli() {
+        let status = |builder: ScannerConfigBuilder| {
+            builder.build().status_codes.map(|codes| codes.to_string())
+        };
+        let builder = || ScannerConfig::builder("example.com");
+
+        assert_eq!(status(builder()), Some("202".to_string()));
+        assert_eq!(
+            status(builder().header_match("Server: nginx")),
+            Some("202".to_string())
+        );
+        assert_eq!(
+            status(builder().match_expr(Some("title~Shop".to_string()))),
+            None
+        );
+        assert_eq!(status(builder().matcher(PanickingMatcher)), None);
+        assert_eq!(
+            status(
+                builder()
+                    .status_codes(Some("200".parse().unwrap()))
+                    .match_expr(Some("title~Shop".to_string()))
+            ),
+            Some("200".to_string())
+        );
+        assert_eq!(
+            status(
+                builder()
+                    .match_expr(Some("title~Shop".to_string()))
+                    .status_codes(Some("202".parse().unwrap()))
+            ),
+            Some("202".to_string())
+        );
+    }
+}
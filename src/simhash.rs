@@ -0,0 +1,109 @@
+use std::collections::BTreeSet;
+
+/// Number of consecutive words hashed into one feature
+const SHINGLE_WORDS: usize = 3;
+
+/// Features of a page: hashes of every run of [`SHINGLE_WORDS`] words
+///
+/// Words are the lowercase alphanumeric runs of the text, so markup, tag
+/// names and attribute values count alongside the visible text while
+/// punctuation and whitespace changes are ignored. A nonce or timestamp only
+/// changes the few shingles it appears in.
+pub fn features(text: &str) -> BTreeSet<u64> {
+    let words: Vec<String> = text
+        .split(|c: char| !c.is_alphanumeric())
+        .filter(|word| !word.is_empty())
+        .map(str::to_lowercase)
+        .collect();
+
+    if words.len() < SHINGLE_WORDS {
+        return words.iter().map(|word| fnv1a(word.as_bytes())).collect();
+    }
+    words
+        .windows(SHINGLE_WORDS)
+        .map(|shingle| fnv1a(shingle.join(" ").as_bytes()))
+        .collect()
+}
+
+/// 64-bit simhash of a set of features
+///
+/// Each bit is set when more features have it set than not, so similar
+/// feature sets end up a small Hamming distance apart.
+pub fn simhash<'a>(features: impl IntoIterator<Item = &'a u64>) -> u64 {
+    let mut weights = [0i64; 64];
+    for feature in features {
+        for (bit, weight) in weights.iter_mut().enumerate() {
+            if feature >> bit & 1 == 1 {
+                *weight += 1;
+            } else {
+                *weight -= 1;
+            }
+        }
+    }
+    weights
+        .iter()
+        .enumerate()
+        .filter(|(_, weight)| **weight > 0)
+        .fold(0, |hash, (bit, _)| hash | 1 << bit)
+}
+
+/// Similarity of two simhashes: the share of equal bits, from 0.0 to 1.0
+pub fn similarity(a: u64, b: u64) -> f64 {
+    1.0 - f64::from((a ^ b).count_ones()) / 64.0
+}
+
+/// FNV-1a, a fast hash that is stable across runs and platforms
+fn fnv1a(bytes: &[u8]) -> u64 {
+    bytes.iter().fold(0xcbf2_9ce4_8422_2325, |hash, &b| {
+        (hash ^ u64::from(b)).wrapping_mul(0x0100_0000_01b3)
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const PAGE: &str = "<html><head><title>Example Shop</title></head><body>\
+        <h1>Welcome to the Example Shop</h1><p>Hand made goods shipped from our small \
+        workshop to your door, every week of the year. Browse the catalogue, pick your \
+        favourites and check out in a minute.</p><form><input name=csrf value=3f9a1c></form>\
+        </body></html>";
+
+    fn hash(text: &str) -> u64 {
+        simhash(&features(text))
+    }
+
+    #[test]
+    fn identical_text_has_distance_zero() {
+        assert_eq!((hash(PAGE) ^ hash(PAGE)).count_ones(), 0);
+        assert_eq!(similarity(hash(PAGE), hash(PAGE)), 1.0);
+    }
+
+    #[test]
+    fn whitespace_and_case_are_ignored() {
+        let reformatted = PAGE.replace("><", ">\n  <").to_uppercase();
+        assert_eq!(hash(&reformatted), hash(PAGE));
+    }
+
+    #[test]
+    fn small_edits_give_a_small_distance() {
+        let edited = PAGE.replace("3f9a1c", "b7e20d");
+        let distance = (hash(PAGE) ^ hash(&edited)).count_ones();
+        assert!(distance > 0 && distance <= 12, "distance {}", distance);
+        assert!(similarity(hash(PAGE), hash(&edited)) >= 0.8);
+    }
+
+    #[test]
+    fn unrelated_text_is_far_apart() {
+        let other = "<html><body><h2>404 Not Found</h2><hr><address>nginx/1.24.0 \
+            (Ubuntu) Server at 203.0.113.7 Port 80</address></body></html>";
+        assert!(similarity(hash(PAGE), hash(other)) < 0.8);
+    }
+
+    #[test]
+    fn short_text_still_has_features() {
+        assert_eq!(features("").len(), 0);
+        assert_eq!(features("hello world").len(), 2);
+        assert_eq!(features("one two three four").len(), 2);
+    }
+}
@@ -0,0 +1,53 @@
+use std::fmt;
+use std::sync::atomic::{AtomicU64, Ordering};
+
+/// Counters describing how a scan's connection attempts went
+///
+/// Shared by every scan task; all counters are cumulative across ranges.
+#[derive(Debug, Default)]
+pub struct ScanStats {
+    /// TCP connection attempts (including retries)
+    pub connect_attempts: AtomicU64,
+    /// Attempts that established a TCP connection
+    pub connected: AtomicU64,
+    /// Attempts that hit the connect timeout
+    pub connect_timeouts: AtomicU64,
+    /// Attempts that failed with an error (refused, unreachable, reset, ...)
+    pub connect_errors: AtomicU64,
+    /// TLS handshakes or HTTP responses that did not arrive in time
+    pub read_timeouts: AtomicU64,
+    /// Probes repeated after a transient error
+    pub retries: AtomicU64,
+    /// Hosts abandoned because the per-host deadline passed
+    pub deadline_exceeded: AtomicU64,
+    /// TLS handshakes that failed (timeouts are counted as read timeouts)
+    pub tls_failures: AtomicU64,
+    /// Certificates that failed verification, probed again without it
+    pub untrusted_certs: AtomicU64,
+}
+
+impl ScanStats {
+    /// Increment one counter
+    pub fn inc(counter: &AtomicU64) {
+        counter.fetch_add(1, Ordering::Relaxed);
+    }
+}
+
+impl fmt::Display for ScanStats {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let get = |counter: &AtomicU64| counter.load(Ordering::Relaxed);
+        write!(
+            f,
+            "{} connect attempts, {} connected, {} connect timeouts, {} connect errors, {} read timeouts, {} TLS handshake failures, {} untrusted certificates, {} retries, {} hosts over deadline",
+            get(&self.connect_attempts),
+            get(&self.connected),
+            get(&self.connect_timeouts),
+            get(&self.connect_errors),
+            get(&self.read_timeouts),
+            get(&self.tls_failures),
+            get(&self.untrusted_certs),
+            get(&self.retries),
+            get(&self.deadline_exceeded),
+        )
+    }
+}
@@ -0,0 +1,236 @@
+use sha2::{Digest, Sha256};
+use std::fs;
+use std::path::PathBuf;
+use std::sync::Arc;
+use tokio_rustls::rustls::client::danger::{
+    HandshakeSignatureValid, ServerCertVerified, ServerCertVerifier,
+};
+use tokio_rustls::rustls::crypto::{self, CryptoProvider};
+use tokio_rustls::rustls::pki_types::pem::PemObject;
+use tokio_rustls::rustls::pki_types::{CertificateDer, ServerName, UnixTime};
+use tokio_rustls::rustls::{ClientConfig, DigitallySignedStruct, RootCertStore, SignatureScheme};
+use tokio_rustls::TlsConnector;
+use x509_parser::prelude::{FromDer, GeneralName, X509Certificate};
+
+/// How the scanner validates certificates presented by scanned hosts
+#[derive(Debug, Clone)]
+pub enum CertVerification {
+    /// Accept any certificate (origins often use self-signed or origin-CA certs)
+    Skip,
+    /// Verify against the operating system trust store
+    SystemRoots,
+    /// Verify against the PEM-encoded CA certificates in the given file
+    CustomRoots(PathBuf),
+}
+
+/// Build a TLS connector for the requested verification mode
+///
+/// # Returns
+/// * `Ok(TlsConnector)` - Connector ready to wrap TCP streams
+/// * `Err` - If no usable root certificates could be loaded
+pub fn build_connector(
+    verification: &CertVerification,
+) -> Result<TlsConnector, Box<dyn std::error::Error>> {
+    let provider = Arc::new(crypto::ring::default_provider());
+    let builder = ClientConfig::builder_with_provider(provider.clone())
+        .with_safe_default_protocol_versions()?;
+
+    let config = match verification {
+        CertVerification::Skip => builder
+            .dangerous()
+            .with_custom_certificate_verifier(Arc::new(NoVerification(provider)))
+            .with_no_client_auth(),
+        CertVerification::SystemRoots => {
+            let mut roots = RootCertStore::empty();
+            let native = rustls_native_certs::load_native_certs();
+            let (added, _) = roots.add_parsable_certificates(native.certs);
+            if added == 0 {
+                return Err("No usable system root certificates found".into());
+            }
+            builder.with_root_certificates(roots).with_no_client_auth()
+        }
+        CertVerification::CustomRoots(path) => {
+            let pem = fs::read(path)
+                .map_err(|e| format!("Failed to read CA file {}: {}", path.display(), e))?;
+            let mut roots = RootCertStore::empty();
+            for cert in CertificateDer::pem_slice_iter(&pem) {
+                let cert = cert.map_err(|e| format!("Invalid PEM in {}: {}", path.display(), e))?;
+                roots.add(cert)?;
+            }
+            if roots.is_empty() {
+                return Err(format!("No certificates found in {}", path.display()).into());
+            }
+            builder.with_root_certificates(roots).with_no_client_auth()
+        }
+    };
+
+    Ok(TlsConnector::from(Arc::new(config)))
+}
+
+/// Convert the target domain into the SNI value sent in the ClientHello
+pub fn server_name(domain: &str) -> Result<ServerName<'static>, Box<dyn std::error::Error>> {
+    ServerName::try_from(domain.to_string())
+        .map_err(|e| format!("Invalid TLS server name '{}': {}", domain, e).into())
+}
+
+/// Certificate verifier that accepts every certificate but still checks
+/// handshake signatures, so the connection is at least internally consistent
+#[derive(Debug)]
+struct NoVerification(Arc<CryptoProvider>);
+
+impl ServerCertVerifier for NoVerification {
+    fn verify_server_cert(
+        &self,
+        _end_entity: &CertificateDer<'_>,
+        _intermediates: &[CertificateDer<'_>],
+        _server_name: &ServerName<'_>,
+        _ocsp_response: &[u8],
+        _now: UnixTime,
+    ) -> Result<ServerCertVerified, tokio_rustls::rustls::Error> {
+        Ok(ServerCertVerified::assertion())
+    }
+
+    fn verify_tls12_signature(
+        &self,
+        message: &[u8],
+        cert: &CertificateDer<'_>,
+        dss: &DigitallySignedStruct,
+    ) -> Result<HandshakeSignatureValid, tokio_rustls::rustls::Error> {
+        crypto::verify_tls12_signature(
+            message,
+            cert,
+            dss,
+            &self.0.signature_verification_algorithms,
+        )
+    }
+
+    fn verify_tls13_signature(
+        &self,
+        message: &[u8],
+        cert: &CertificateDer<'_>,
+        dss: &DigitallySignedStruct,
+    ) -> Result<HandshakeSignatureValid, tokio_rustls::rustls::Error> {
+        crypto::verify_tls13_signature(
+            message,
+            cert,
+            dss,
+            &self.0.signature_verification_algorithms,
+        )
+    }
+
+    fn supported_verify_schemes(&self) -> Vec<SignatureScheme> {
+        self.0.signature_verification_algorithms.supported_schemes()
+    }
+}
+
+/// Certificate-based origin matcher
+///
+/// Identifies an origin purely from the certificate it presents for the
+/// target SNI, without sending any HTTP request.
+#[derive(Debug)]
+pub struct CertMatcher {
+    domain: String,
+    match_names: bool,
+    sha256: Option<[u8; 32]>,
+    spki_sha256: Option<[u8; 32]>,
+}
+
+impl CertMatcher {
+    /// Create a certificate matcher
+    ///
+    /// # Arguments
+    /// * `domain` - Target domain the SAN/CN must cover
+    /// * `match_names` - Whether a SAN/CN covering `domain` counts as a hit
+    /// * `sha256` - Expected certificate SHA-256 fingerprint (hex, colons allowed)
+    /// * `spki_sha256` - Expected SubjectPublicKeyInfo SHA-256 hash (hex, colons allowed)
+    pub fn new(
+        domain: &str,
+        match_names: bool,
+        sha256: Option<&str>,
+        spki_sha256: Option<&str>,
+    ) -> Result<Self, Box<dyn std::error::Error>> {
+        Ok(Self {
+            domain: domain.trim_end_matches('.').to_ascii_lowercase(),
+            match_names,
+            sha256: sha256.map(parse_sha256_hex).transpose()?,
+            spki_sha256: spki_sha256.map(parse_sha256_hex).transpose()?,
+        })
+    }
+
+    /// Check a DER-encoded leaf certificate against the configured rules
+    ///
+    /// # Returns
+    /// * `Some(rule)` - Name of the rule that matched (e.g. `cert-san:example.com`)
+    /// * `None` - If no rule matched or the certificate could not be parsed
+    pub fn check(&self, der: &[u8]) -> Option<String> {
+        if let Some(expected) = self.sha256 {
+            if Sha256::digest(der).as_slice() == expected {
+                return Some("cert-sha256".to_string());
+            }
+        }
+
+        let (_, cert) = X509Certificate::from_der(der).ok()?;
+
+        if let Some(expected) = self.spki_sha256 {
+            if Sha256::digest(cert.public_key().raw).as_slice() == expected {
+                return Some("spki-sha256".to_string());
+            }
+        }
+
+        if self.match_names {
+            let mut names: Vec<&str> = Vec::new();
+            if let Ok(Some(san)) = cert.subject_alternative_name() {
+                for name in &san.value.general_names {
+                    if let GeneralName::DNSName(dns) = name {
+                        names.push(dns);
+                    }
+                }
+            }
+
+            // Only fall back to the subject CN when the certificate carries no DNS SANs
+            let source = if names.is_empty() {
+                names.extend(
+                    cert.subject()
+                        .iter_common_name()
+                        .filter_map(|cn| cn.as_str().ok()),
+                );
+                "cert-cn"
+            } else {
+                "cert-san"
+            };
+
+            if let Some(name) = names.iter().find(|n| name_covers(n, &self.domain)) {
+                return Some(format!("{}:{}", source, name));
+            }
+        }
+
+        None
+    }
+}
+
+/// Check whether a certificate name (possibly a `*.` wildcard) covers a domain
+///
+/// Wildcards only cover a single left-most label, as in RFC 6125.
+fn name_covers(pattern: &str, domain: &str) -> bool {
+    let pattern = pattern.trim_end_matches('.').to_ascii_lowercase();
+    match pattern.strip_prefix("*.") {
+        Some(suffix) => domain
+            .split_once('.')
+            .is_some_and(|(label, rest)| !label.is_empty() && rest == suffix),
+        None => pattern == domain,
+    }
+}
+
+/// Parse a SHA-256 digest written as hex, with optional `:` separators
+pub(crate) fn parse_sha256_hex(value: &str) -> Result<[u8; 32], Box<dyn std::error::Error>> {
+    let hex: String = value.chars().filter(|c| *c != ':').collect();
+    if hex.len() != 64 || !hex.chars().all(|c| c.is_ascii_hexdigit()) {
+        return Err(format!("Invalid SHA-256 value '{}': expected 64 hex digits", value).into());
+    }
+
+    let mut digest = [0u8; 32];
+    for (i, byte) in digest.iter_mut().enumerate() {
+        *byte = u8::from_str_radix(&hex[i * 2..i * 2 + 2], 16)?;
+    }
+    Ok(digest)
+}